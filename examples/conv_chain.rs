@@ -1,36 +1,40 @@
-use convchain::slow;
-use image::GrayImage;
-use serde::Deserialize;
-use std::fs;
+// The example mirrors the reference implementation it runs.
+#![allow(clippy::explicit_counter_loop, clippy::expect_fun_call)]
+
+use convchain::slow::conv_chain as conv_chain_slow;
+use convchain::{ConvChainSample, JobManifest, Palette};
 use std::path::PathBuf;
 
 const RESOURCES_PATH: &str = "resources";
 
 fn main() {
-    let doc = read_samples();
+    let manifest =
+        JobManifest::load(get_file_path("samples", "xml")).expect("unable to read samples.xml");
 
     let mut pass = 1;
-    for row in doc.samples {
+    for row in manifest.samples {
         let file_path = get_file_path(&row.name, "png");
         assert!(file_path.exists());
 
-        let gray = image::open(file_path.clone())
-            .expect(format!("failed to open {:?}", file_path).as_str())
-            .to_luma8();
-        let sample = to_array(&gray);
+        let image = image::open(file_path.clone())
+            .expect(format!("failed to open {:?}", file_path).as_str());
+        let sample = ConvChainSample::from_dynamic_image(&image, 0).expect("unable to read sample");
+        let values = sample.as_slice().to_vec();
 
         for k in 0..row.screenshots {
             println!("> {} {}", row.name, k);
             let result = conv_chain_slow(
-                &sample,
-                gray.width(),
-                gray.height(),
+                &values,
+                sample.width,
+                sample.height,
                 row.receptor_size,
                 row.temperature,
                 row.output_size,
                 row.iterations,
             );
-            let output = to_image(row.output_size, row.output_size, result);
+            let output = ConvChainSample::from_vec(result, row.output_size, row.output_size)
+                .expect("invalid output size")
+                .to_luma_image(&Palette::default());
             output
                 .save(format!(
                     "{} {} t={} i={} {}.png",
@@ -43,24 +47,6 @@ fn main() {
     }
 }
 
-fn to_array(img: &GrayImage) -> Vec<bool> {
-    let vec: Vec<bool> = img.iter().map(|&x| x > 0).collect();
-    assert_eq!(vec.len(), (img.width() * img.height()) as usize);
-    vec
-}
-
-fn to_image(width: u32, height: u32, array: Vec<bool>) -> GrayImage {
-    let bytes = array.iter().map(|&x| if x { 255 } else { 0 }).collect();
-    GrayImage::from_raw(width, height, bytes).expect("unable to create image")
-}
-
-fn read_samples() -> Samples {
-    let file_path = get_file_path("samples", "xml");
-    let xml = fs::read_to_string(file_path.clone())
-        .expect(format!("Could not read {:?}", file_path).as_str());
-    quick_xml::de::from_str(xml.as_str()).unwrap()
-}
-
 fn get_file_path<S, E>(name: S, extension: E) -> PathBuf
 where
     S: AsRef<str>,
@@ -73,45 +59,3 @@ where
     .iter()
     .collect()
 }
-
-#[derive(Debug, Deserialize)]
-struct Samples {
-    #[serde(rename = "$value")]
-    samples: Vec<Sample>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Sample {
-    name: String,
-    #[serde(default = "default_receptor_size")]
-    receptor_size: u32,
-    #[serde(default = "default_temperature")]
-    temperature: f64,
-    #[serde(default = "default_iterations")]
-    iterations: usize,
-    #[serde(default = "default_screenshot_count")]
-    screenshots: usize,
-    #[serde(default = "default_output_size")]
-    output_size: u32,
-}
-
-fn default_receptor_size() -> u32 {
-    2
-}
-
-fn default_temperature() -> f64 {
-    1.
-}
-
-fn default_iterations() -> usize {
-    2
-}
-
-fn default_screenshot_count() -> usize {
-    1
-}
-
-fn default_output_size() -> u32 {
-    32
-}
@@ -0,0 +1,182 @@
+use crate::{ConvChainSample, SampleError};
+
+/// The characters used for set and unset values in ASCII art samples.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AsciiMapping {
+    pub set: char,
+    pub unset: char,
+}
+
+impl Default for AsciiMapping {
+    fn default() -> Self {
+        Self {
+            set: '#',
+            unset: '.',
+        }
+    }
+}
+
+impl ConvChainSample<'static> {
+    /// Parses a sample from ASCII art. Blank lines before and after the art are ignored, but
+    /// every other character is significant. Rows in errors are numbered by the lines of `text`.
+    ///
+    /// ```
+    /// use convchain::{AsciiMapping, ConvChainSample};
+    ///
+    /// let mapping = AsciiMapping::default();
+    /// let sample = ConvChainSample::from_ascii("####\n#...\n#.#.\n#...", &mapping).unwrap();
+    ///
+    /// assert_eq!((sample.width, sample.height), (4, 4));
+    /// assert_eq!(sample.to_ascii(&mapping), "####\n#...\n#.#.\n#...\n");
+    /// ```
+    pub fn from_ascii(text: &str, mapping: &AsciiMapping) -> Result<Self, SampleError> {
+        if mapping.set == mapping.unset {
+            return Err(SampleError::AmbiguousCharacter {
+                character: mapping.set,
+            });
+        }
+
+        // Whitespace-only lines are blank, unless whitespace is mapped to a value.
+        let is_blank = |line: &str| {
+            line.chars()
+                .all(|c| c.is_whitespace() && c != mapping.set && c != mapping.unset)
+        };
+        let lines: Vec<_> = text.lines().collect();
+        let first = lines.iter().position(|line| !is_blank(line)).unwrap_or(0);
+        let last = lines
+            .iter()
+            .rposition(|line| !is_blank(line))
+            .map_or(0, |l| l + 1);
+
+        let rows = lines[first..last.max(first)]
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                line.chars()
+                    .enumerate()
+                    .map(|(column, character)| match character {
+                        c if c == mapping.set => Ok(true),
+                        c if c == mapping.unset => Ok(false),
+                        _ => Err(SampleError::InvalidCharacter {
+                            row: first + index,
+                            column,
+                            character,
+                        }),
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<Vec<bool>>, _>>()?;
+
+        Self::from_rows(rows).map_err(|error| match error {
+            SampleError::RaggedRow {
+                row,
+                expected,
+                actual,
+            } => SampleError::RaggedRow {
+                row: first + row,
+                expected,
+                actual,
+            },
+            error => error,
+        })
+    }
+}
+
+impl<'a> ConvChainSample<'a> {
+    /// Renders the sample as ASCII art, with every row terminated by a newline.
+    pub fn to_ascii(&self, mapping: &AsciiMapping) -> String {
+        let width = self.width as usize;
+        let mut text = String::with_capacity((width + 1) * self.height as usize);
+        for row in self.as_slice().chunks(width) {
+            text.extend(
+                row.iter()
+                    .map(|&v| if v { mapping.set } else { mapping.unset }),
+            );
+            text.push('\n');
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_mapping_roundtrip() {
+        let mapping = AsciiMapping {
+            set: 'o',
+            unset: 'X',
+        };
+        let sample =
+            ConvChainSample::from_rows(vec![vec![true, false, true], vec![false, false, true]])
+                .unwrap();
+
+        let text = sample.to_ascii(&mapping);
+        assert_eq!(text, "oXo\nXXo\n");
+        assert_eq!(ConvChainSample::from_ascii(&text, &mapping), Ok(sample));
+    }
+
+    #[test]
+    fn reports_invalid_characters() {
+        let result = ConvChainSample::from_ascii("##\n#?", &AsciiMapping::default());
+        assert_eq!(
+            result,
+            Err(SampleError::InvalidCharacter {
+                row: 1,
+                column: 1,
+                character: '?'
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_spaces_and_line_numbers() {
+        let mapping = AsciiMapping {
+            set: '#',
+            unset: ' ',
+        };
+        let sample = ConvChainSample::from_ascii("\n# #\n ##\n\n", &mapping).unwrap();
+        assert_eq!(sample.to_ascii(&mapping), "# #\n ##\n");
+
+        let result = ConvChainSample::from_ascii("\n\n##\n#\n##", &AsciiMapping::default());
+        assert_eq!(
+            result,
+            Err(SampleError::RaggedRow {
+                row: 3,
+                expected: 2,
+                actual: 1
+            })
+        );
+        let result = ConvChainSample::from_ascii("\n  ##\n", &AsciiMapping::default());
+        assert_eq!(
+            result,
+            Err(SampleError::InvalidCharacter {
+                row: 1,
+                column: 0,
+                character: ' '
+            })
+        );
+        let ambiguous = AsciiMapping {
+            set: '#',
+            unset: '#',
+        };
+        assert_eq!(
+            ConvChainSample::from_ascii("##", &ambiguous),
+            Err(SampleError::AmbiguousCharacter { character: '#' })
+        );
+    }
+
+    #[test]
+    fn reports_ragged_rows() {
+        let result = ConvChainSample::from_ascii("##\n#.#\n", &AsciiMapping::default());
+        assert_eq!(
+            result,
+            Err(SampleError::RaggedRow {
+                row: 1,
+                expected: 2,
+                actual: 3
+            })
+        );
+    }
+}
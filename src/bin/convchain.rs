@@ -0,0 +1,257 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use convchain::{
+    AsciiMapping, Boundary, ConvChain, ConvChainModel, ConvChainSample, Job, JobManifest,
+    NetpbmFormat, Palette, Symmetry, DEFAULT_SMOOTHING,
+};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Bitmap generation from a single example with convolutions and MCMC.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Generates outputs from a single sample.
+    Generate(GenerateArgs),
+    /// Processes all jobs defined in a job file such as `resources/samples.xml`.
+    Batch(BatchArgs),
+}
+
+#[derive(Debug, Args)]
+struct GenerateArgs {
+    /// The sample image (PNG, PBM, PGM or ASCII art in a .txt file).
+    input: PathBuf,
+    /// The output file; the format is derived from its extension.
+    /// When generating multiple outputs, their index is appended to the file name.
+    #[arg(short, long, default_value = "output.png")]
+    output: PathBuf,
+    /// The width and height of the output.
+    #[arg(short, long, default_value_t = 32)]
+    size: u32,
+    #[command(flatten)]
+    options: ChainOptions,
+    /// The number of outputs to generate.
+    #[arg(short = 'n', long, default_value_t = 1)]
+    count: usize,
+}
+
+#[derive(Debug, Args)]
+struct BatchArgs {
+    /// The job file (XML, JSON or TOML); sample images are resolved relative to its directory.
+    jobs: PathBuf,
+    /// The directory to write the outputs to.
+    #[arg(short, long, default_value = ".")]
+    output_dir: PathBuf,
+    /// The seed of the random number generator; every output uses its own seed derived from it.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+struct ChainOptions {
+    /// The size of the receptor.
+    #[arg(short, long, default_value_t = 2)]
+    receptor_size: u32,
+    /// The temperature of the Metropolis algorithm.
+    #[arg(short, long, default_value_t = 1.0)]
+    temperature: f64,
+    /// The number of iterations over the whole field.
+    #[arg(short, long, default_value_t = 2)]
+    iterations: usize,
+    /// The seed of the random number generator; every output uses its own seed derived from it.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// The symmetries used to augment the sample's patterns.
+    #[arg(long, value_enum, default_value_t = SymmetryArg::Full)]
+    symmetry: SymmetryArg,
+    /// The treatment of receptors at the edges of the output.
+    #[arg(long, value_enum, default_value_t = BoundaryArg::Periodic)]
+    boundary: BoundaryArg,
+    /// Sample pixels brighter than this value are set.
+    #[arg(long, default_value_t = 0)]
+    threshold: u8,
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum SymmetryArg {
+    None,
+    Reflection,
+    Rotation,
+    Full,
+}
+
+impl From<SymmetryArg> for Symmetry {
+    fn from(value: SymmetryArg) -> Self {
+        match value {
+            SymmetryArg::None => Symmetry::None,
+            SymmetryArg::Reflection => Symmetry::Reflection,
+            SymmetryArg::Rotation => Symmetry::Rotation,
+            SymmetryArg::Full => Symmetry::Full,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum BoundaryArg {
+    Periodic,
+    Open,
+    Horizontal,
+    Vertical,
+}
+
+impl From<BoundaryArg> for Boundary {
+    fn from(value: BoundaryArg) -> Self {
+        match value {
+            BoundaryArg::Periodic => Boundary::Periodic,
+            BoundaryArg::Open => Boundary::Open,
+            BoundaryArg::Horizontal => Boundary::Horizontal,
+            BoundaryArg::Vertical => Boundary::Vertical,
+        }
+    }
+}
+
+fn main() {
+    let result = match Cli::parse().command {
+        Command::Generate(args) => generate(args),
+        Command::Batch(args) => batch(args),
+    };
+
+    if let Err(error) = result {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn generate(args: GenerateArgs) -> Result<()> {
+    let options = &args.options;
+    // The options are subject to the same limits as the jobs of a job file.
+    Job {
+        receptor_size: options.receptor_size,
+        temperature: options.temperature,
+        output_size: args.size,
+        ..Job::new(args.input.display().to_string())
+    }
+    .validate()?;
+
+    let sample = load_sample(&args.input, options.threshold)?;
+    let model = Arc::new(ConvChainModel::learn(
+        &sample,
+        options.receptor_size,
+        options.symmetry.into(),
+        DEFAULT_SMOOTHING,
+    ));
+
+    for k in 0..args.count {
+        let mut chain = ConvChain::from_model(model.clone(), args.size, options.temperature)
+            .with_boundary(options.boundary.into());
+        if let Some(seed) = options.seed {
+            chain = chain.with_seed(seed.wrapping_add(k as u64));
+        }
+        chain.process(options.iterations);
+
+        let path = if args.count > 1 {
+            indexed_path(&args.output, k)
+        } else {
+            args.output.clone()
+        };
+        save_sample(&chain.as_sample(), &path)?;
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
+
+fn batch(args: BatchArgs) -> Result<()> {
+    let mut manifest = JobManifest::load(&args.jobs)
+        .map_err(|e| format!("unable to read {}: {}", args.jobs.display(), e))?;
+    let resources = args.jobs.parent().unwrap_or_else(|| Path::new("."));
+
+    if let Some(seed) = args.seed {
+        for (pass, job) in (1u64..).zip(manifest.samples.iter_mut()) {
+            job.seed.get_or_insert(seed.wrapping_add(pass << 32));
+        }
+    }
+
+    let results = manifest.execute_in(resources)?;
+
+    for (pass, result) in (1..).zip(results) {
+        let job = result.job;
+        for (k, output) in result.outputs?.iter().enumerate() {
+            let path = args.output_dir.join(format!(
+                "{} {} t={} i={} {}.png",
+                pass, job.name, job.temperature, job.iterations, k
+            ));
+            save_sample(output, &path)?;
+            println!("{}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn load_sample(path: &Path, threshold: u8) -> Result<ConvChainSample<'static>> {
+    let context = |e: &dyn Error| format!("unable to read {}: {}", path.display(), e);
+    let sample = match extension(path).as_str() {
+        "pbm" | "pgm" => {
+            let file = File::open(path).map_err(|e| context(&e))?;
+            ConvChainSample::read_netpbm(BufReader::new(file), threshold)
+                .map_err(|e| context(&e))?
+        }
+        "txt" => {
+            let text = fs::read_to_string(path).map_err(|e| context(&e))?;
+            ConvChainSample::from_ascii(&text, &AsciiMapping::default()).map_err(|e| context(&e))?
+        }
+        _ => {
+            let image = image::open(path).map_err(|e| context(&e))?;
+            ConvChainSample::from_dynamic_image(&image, threshold).map_err(|e| context(&e))?
+        }
+    };
+    Ok(sample)
+}
+
+fn save_sample(sample: &ConvChainSample, path: &Path) -> Result<()> {
+    let context = |e: &dyn Error| format!("unable to write {}: {}", path.display(), e);
+    match extension(path).as_str() {
+        "pbm" => write_netpbm(sample, path, NetpbmFormat::Pbm).map_err(|e| context(&e))?,
+        "pgm" => write_netpbm(sample, path, NetpbmFormat::Pgm).map_err(|e| context(&e))?,
+        "txt" => {
+            fs::write(path, sample.to_ascii(&AsciiMapping::default())).map_err(|e| context(&e))?
+        }
+        _ => sample
+            .to_luma_image(&Palette::default())
+            .save(path)
+            .map_err(|e| context(&e))?,
+    }
+    Ok(())
+}
+
+fn write_netpbm(sample: &ConvChainSample, path: &Path, format: NetpbmFormat) -> io::Result<()> {
+    let file = File::create(path)?;
+    sample.write_netpbm(BufWriter::new(file), format)
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn indexed_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, index),
+    };
+    path.with_file_name(name)
+}
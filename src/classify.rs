@@ -0,0 +1,112 @@
+use crate::{Boundary, ConvChainModel, ConvChainSample};
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+/// Classifies fields by the model that assigns them the highest
+/// [normalized log-likelihood](ConvChainModel::normalized_log_likelihood).
+#[derive(Debug, Clone)]
+pub struct Classifier<L> {
+    models: Vec<(L, Arc<ConvChainModel>)>,
+}
+
+impl<L> Default for Classifier<L> {
+    fn default() -> Self {
+        Self { models: Vec::new() }
+    }
+}
+
+impl<L> Classifier<L> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, label: L, model: Arc<ConvChainModel>) {
+        self.models.push((label, model));
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// Scores the field under every model, best match first. Models that find no complete
+    /// pattern in the field cannot score it and are left out.
+    pub fn scores(&self, field: &ConvChainSample, boundary: Boundary) -> Vec<(&L, f64)> {
+        let mut scores: Vec<_> = self
+            .models
+            .iter()
+            .map(|(label, model)| (label, model.normalized_log_likelihood(field, boundary)))
+            .filter(|&(_, score)| score.is_finite())
+            .collect();
+        scores.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        scores
+    }
+
+    /// Determines the label of the best matching model and its score, or `None` if no model
+    /// can score the field.
+    pub fn classify(&self, field: &ConvChainSample, boundary: Boundary) -> Option<(&L, f64)> {
+        self.scores(field, boundary).into_iter().next()
+    }
+}
+
+impl<L> FromIterator<(L, Arc<ConvChainModel>)> for Classifier<L> {
+    fn from_iter<I: IntoIterator<Item = (L, Arc<ConvChainModel>)>>(iter: I) -> Self {
+        Self {
+            models: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::resource;
+
+    #[test]
+    fn resources_classify_as_themselves() {
+        let names = ["Chess", "Maze", "Cave", "Rooms", "Smile"];
+        let samples: Vec<_> = names.iter().map(|name| resource(name)).collect();
+        let classifier: Classifier<&str> = names
+            .iter()
+            .zip(&samples)
+            .map(|(&name, sample)| (name, Arc::new(ConvChainModel::new(sample, 3))))
+            .collect();
+
+        for (name, sample) in names.iter().zip(&samples) {
+            let (label, _) = classifier.classify(sample, Boundary::Periodic).unwrap();
+            assert_eq!(label, name);
+        }
+    }
+
+    #[test]
+    fn empty_classifier_has_no_label() {
+        let classifier = Classifier::<()>::new();
+        let field = ConvChainSample::new(&[true; 4], 2, 2);
+        assert!(classifier.classify(&field, Boundary::Periodic).is_none());
+    }
+
+    #[test]
+    fn fields_without_patterns_are_rejected() {
+        let sample = resource("Chess");
+        let small = Arc::new(ConvChainModel::new(&sample, 2));
+        let large = Arc::new(ConvChainModel::new(&sample, 3));
+        let field = ConvChainSample::new(&[true; 4], 2, 2);
+        assert_eq!(
+            large.normalized_log_likelihood(&field, Boundary::Open),
+            f64::NEG_INFINITY
+        );
+
+        let mut classifier = Classifier::new();
+        classifier.add("large", large);
+        assert!(classifier.classify(&field, Boundary::Open).is_none());
+        classifier.add("small", small);
+        let scores = classifier.scores(&field, Boundary::Open);
+        assert_eq!(scores.len(), 1);
+        assert_eq!(*scores[0].0, "small");
+    }
+}
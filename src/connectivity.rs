@@ -0,0 +1,922 @@
+use crate::{Boundary, ConvChain, ConvChainSample};
+use std::collections::VecDeque;
+
+/// Which elements count as neighbors when labeling regions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Connectivity {
+    /// Elements sharing an edge.
+    #[default]
+    Four,
+    /// Elements sharing an edge or a corner.
+    Eight,
+}
+
+impl Connectivity {
+    fn steps(self) -> &'static [(i64, i64)] {
+        match self {
+            Connectivity::Four => &[(1, 0), (0, 1), (-1, 0), (0, -1)],
+            Connectivity::Eight => &[
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+            ],
+        }
+    }
+
+    /// The neighbors of an element of a `width × height` field.
+    pub(crate) fn neighbors(
+        self,
+        cell: usize,
+        width: u32,
+        height: u32,
+        boundary: Boundary,
+    ) -> impl Iterator<Item = usize> {
+        let (width, height) = (width as i64, height as i64);
+        let (x, y) = (cell as i64 % width, cell as i64 / width);
+        self.steps().iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+            let nx = match boundary.wraps_horizontally() {
+                true => nx.rem_euclid(width),
+                false if (0..width).contains(&nx) => nx,
+                false => return None,
+            };
+            let ny = match boundary.wraps_vertically() {
+                true => ny.rem_euclid(height),
+                false if (0..height).contains(&ny) => ny,
+                false => return None,
+            };
+            Some((ny * width + nx) as usize)
+        })
+    }
+}
+
+/// The connected regions of the elements of one value in a field, e.g. the floor of a cave.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Components {
+    field: Vec<bool>,
+    width: u32,
+    height: u32,
+    value: bool,
+    connectivity: Connectivity,
+    boundary: Boundary,
+    labels: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+}
+
+/// A field whose regions were connected by carving paths, see [`Components::connect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Carving {
+    pub field: ConvChainSample<'static>,
+    /// The elements whose value was changed, in the order they were carved.
+    pub carved: Vec<(u32, u32)>,
+}
+
+impl Components {
+    /// Labels the regions of elements of the specified value. Along axes where the boundary
+    /// wraps around, regions continue across the edges of the field.
+    pub fn new(
+        field: &ConvChainSample,
+        value: bool,
+        connectivity: Connectivity,
+        boundary: Boundary,
+    ) -> Self {
+        let mut components = Self {
+            field: field.as_slice().to_vec(),
+            width: field.width,
+            height: field.height,
+            value,
+            connectivity,
+            boundary,
+            labels: Vec::new(),
+            sizes: Vec::new(),
+        };
+        components.relabel();
+        components
+    }
+
+    fn relabel(&mut self) {
+        self.labels = vec![None; self.field.len()];
+        self.sizes.clear();
+
+        let mut stack = Vec::new();
+        for start in 0..self.field.len() {
+            if self.field[start] != self.value || self.labels[start].is_some() {
+                continue;
+            }
+
+            let label = self.sizes.len();
+            let mut size = 0;
+            self.labels[start] = Some(label);
+            stack.push(start);
+            while let Some(cell) = stack.pop() {
+                size += 1;
+                for neighbor in self.neighbors(cell) {
+                    if self.field[neighbor] == self.value && self.labels[neighbor].is_none() {
+                        self.labels[neighbor] = Some(label);
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            self.sizes.push(size);
+        }
+    }
+
+    /// The number of regions.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+
+    /// The label of the region containing the element, if it has the labeled value.
+    pub fn label(&self, x: u32, y: u32) -> Option<usize> {
+        self.labels[(y * self.width + x) as usize]
+    }
+
+    /// The number of elements of every region, indexed by label.
+    #[inline]
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// The label of the largest region; ties go to the region found first in row-major order.
+    pub fn largest(&self) -> Option<usize> {
+        (0..self.sizes.len())
+            .rev()
+            .max_by_key(|&label| self.sizes[label])
+    }
+
+    /// Inverts all regions with fewer than `min_size` elements.
+    pub fn remove_islands(&self, min_size: usize) -> ConvChainSample<'static> {
+        self.retain(|label| self.sizes[label] >= min_size)
+    }
+
+    /// Inverts all regions but the largest one.
+    pub fn keep_largest(&self) -> ConvChainSample<'static> {
+        let largest = self.largest();
+        self.retain(|label| Some(label) == largest)
+    }
+
+    fn retain<F>(&self, keep: F) -> ConvChainSample<'static>
+    where
+        F: Fn(usize) -> bool,
+    {
+        let values = self
+            .field
+            .iter()
+            .zip(&self.labels)
+            .map(|(&v, &label)| label.map_or(v, |label| if keep(label) { v } else { !v }));
+        ConvChainSample::from_row_major(values, self.width, self.height)
+            .expect("the field keeps its size")
+    }
+
+    /// Connects all regions to the largest one by carving, for every other region, the path
+    /// with the fewest elements of the other value, possibly leading through further regions.
+    pub fn connect(&self) -> Carving {
+        self.connect_around(None)
+    }
+
+    /// Connects the regions like [`Self::connect`], but never carves through the elements set
+    /// in the `fixed` mask. Regions enclosed by fixed elements stay disconnected.
+    fn connect_around(&self, fixed: Option<&[bool]>) -> Carving {
+        let mut field = self.field.clone();
+        let mut carved = Vec::new();
+        if let Some(largest) = self.largest().filter(|_| self.len() > 1) {
+            self.carve_paths(largest, &mut field, fixed, &mut carved);
+        }
+
+        Carving {
+            field: ConvChainSample::from_vec(field, self.width, self.height)
+                .expect("the field keeps its size"),
+            carved,
+        }
+    }
+
+    fn carve_paths(
+        &self,
+        largest: usize,
+        field: &mut [bool],
+        fixed: Option<&[bool]>,
+        carved: &mut Vec<(u32, u32)>,
+    ) {
+        // A 0-1 breadth-first search from the largest region, where stepping onto an element
+        // costs one if it must be carved, and fixed elements of the other value are impassable.
+        let mut costs = vec![usize::MAX; field.len()];
+        let mut parents = vec![None; field.len()];
+        let mut queue = VecDeque::new();
+        for (cell, &label) in self.labels.iter().enumerate() {
+            if label == Some(largest) {
+                costs[cell] = 0;
+                queue.push_back(cell);
+            }
+        }
+        while let Some(cell) = queue.pop_front() {
+            for neighbor in self.neighbors(cell) {
+                let step = (field[neighbor] != self.value) as usize;
+                if step == 1 && fixed.is_some_and(|fixed| fixed[neighbor]) {
+                    continue;
+                }
+                if costs[cell] + step < costs[neighbor] {
+                    costs[neighbor] = costs[cell] + step;
+                    parents[neighbor] = Some(cell);
+                    if step == 0 {
+                        queue.push_front(neighbor);
+                    } else {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+
+        // Every region follows the path from its first element back to the largest region.
+        let mut reached = vec![false; self.len()];
+        reached[largest] = true;
+        for (cell, &label) in self.labels.iter().enumerate() {
+            let label = match label {
+                Some(label) if !reached[label] => label,
+                _ => continue,
+            };
+            reached[label] = true;
+
+            let mut cell = cell;
+            while let Some(parent) = parents[cell] {
+                if field[parent] != self.value {
+                    field[parent] = self.value;
+                    carved.push((parent as u32 % self.width, parent as u32 / self.width));
+                }
+                cell = parent;
+            }
+        }
+    }
+
+    fn neighbors(&self, cell: usize) -> impl Iterator<Item = usize> {
+        self.connectivity
+            .neighbors(cell, self.width, self.height, self.boundary)
+    }
+}
+
+impl Carving {
+    /// Lets the elements within `radius` of the carved paths adapt to them by running
+    /// `iterations` of the chain, which must have the size of the field. The carved elements
+    /// and everything farther away stay fixed. Since the chain's seed re-initializes its field,
+    /// call [`ConvChain::with_seed`] before passing the chain.
+    pub fn relax(
+        &self,
+        chain: ConvChain,
+        radius: u32,
+        iterations: usize,
+    ) -> ConvChainSample<'static> {
+        let (width, height) = (self.field.width as i64, self.field.height as i64);
+        let mut fixed = vec![true; self.field.as_slice().len()];
+        let (r, boundary) = (radius as i64, chain.boundary());
+        for &(x, y) in &self.carved {
+            for ny in y as i64 - r..=y as i64 + r {
+                for nx in x as i64 - r..=x as i64 + r {
+                    let wraps = (boundary.wraps_horizontally(), boundary.wraps_vertically());
+                    if (!wraps.0 && !(0..width).contains(&nx))
+                        || (!wraps.1 && !(0..height).contains(&ny))
+                    {
+                        continue;
+                    }
+                    let (nx, ny) = (nx.rem_euclid(width), ny.rem_euclid(height));
+                    fixed[(ny * width + nx) as usize] = false;
+                }
+            }
+        }
+        for &(x, y) in &self.carved {
+            fixed[(y as i64 * width + x as i64) as usize] = true;
+        }
+
+        let mask = ConvChainSample::new(&fixed, self.field.width, self.field.height);
+        let mut chain = chain.with_field(&self.field).with_fixed(&mask);
+        chain.process(iterations);
+        chain.as_sample().into_owned()
+    }
+}
+
+/// A constraint on the regions of a field that a chain maintains while sampling by rejecting
+/// all flips that would violate it, see [`ConvChain::with_constraint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectivityConstraint {
+    /// Keeps all elements of the value in a single region, e.g. a maze floor in which every
+    /// cell is reachable. If the field is not connected when processing starts, e.g. initially,
+    /// its regions are connected by carving paths around the fixed elements first. Regions that
+    /// the fixed elements enclose are cleared, unless they contain fixed elements themselves,
+    /// in which case they are left as they are.
+    Connected {
+        value: bool,
+        connectivity: Connectivity,
+    },
+    /// Keeps the regions containing the designated elements apart, e.g. two cave systems.
+    /// The designated elements always have the value; those fixed to the other value are ignored.
+    /// Regions containing several of them when processing starts, e.g. initially, are cleared
+    /// except for the designated and fixed elements; after that, they are never merged. Regions
+    /// that fixed elements connect stay connected.
+    Separated {
+        value: bool,
+        connectivity: Connectivity,
+        elements: Vec<(u32, u32)>,
+    },
+}
+
+/// The regions of a field while a constrained chain is processing.
+pub(crate) struct ConstraintState {
+    value: bool,
+    connectivity: Connectivity,
+    size: usize,
+    boundary: Boundary,
+    /// The number of elements of the value.
+    count: usize,
+    /// The regions to keep apart, if any.
+    separated: Option<Separated>,
+    /// Visit markers, the searches that visited the elements and their queues,
+    /// reused across flips.
+    visited: Vec<u32>,
+    stamp: u32,
+    owners: Vec<usize>,
+    queues: Vec<VecDeque<usize>>,
+}
+
+/// A union-find forest of the regions of the value. Since removing elements is not tracked,
+/// regions may be considered connected although they no longer are, which only makes the
+/// constraint stricter until processing starts again.
+struct Separated {
+    parents: Vec<usize>,
+    /// Whether the region of a root contains a designated element.
+    seeded: Vec<bool>,
+    designated: Vec<bool>,
+}
+
+/// The 8-neighborhood of an element, in order around it; odd positions share an edge with it.
+const RING: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+];
+
+impl ConstraintState {
+    /// Prepares the field of the specified size to satisfy the constraint, without changing
+    /// the elements set in the `fixed` mask.
+    pub fn new(
+        constraint: &ConnectivityConstraint,
+        field: &mut [bool],
+        fixed: Option<&[bool]>,
+        size: usize,
+        boundary: Boundary,
+    ) -> Self {
+        let is_fixed = |cell: usize| fixed.is_some_and(|fixed| fixed[cell]);
+        let (value, connectivity) = match *constraint {
+            ConnectivityConstraint::Connected {
+                value,
+                connectivity,
+            }
+            | ConnectivityConstraint::Separated {
+                value,
+                connectivity,
+                ..
+            } => (value, connectivity),
+        };
+
+        let mut separated = None;
+        match constraint {
+            ConnectivityConstraint::Connected { .. } => {
+                let sample = ConvChainSample::new(field, size as u32, size as u32);
+                let components = Components::new(&sample, value, connectivity, boundary);
+                if components.len() > 1 {
+                    let carving = components.connect_around(fixed);
+                    let components = Components::new(&carving.field, value, connectivity, boundary);
+                    let mut anchored = vec![false; components.len()];
+                    anchored[components.largest().expect("the regions were connected")] = true;
+                    for (cell, &label) in components.labels.iter().enumerate() {
+                        if let Some(label) = label.filter(|_| is_fixed(cell)) {
+                            anchored[label] = true;
+                        }
+                    }
+                    for (cell, &label) in components.labels.iter().enumerate() {
+                        let enclosed = label.is_some_and(|label| !anchored[label]);
+                        field[cell] = carving.field.as_slice()[cell] != enclosed;
+                    }
+                }
+            }
+            ConnectivityConstraint::Separated { elements, .. } => {
+                let mut designated = vec![false; field.len()];
+                for &(x, y) in elements {
+                    let cell = y as usize * size + x as usize;
+                    if is_fixed(cell) && field[cell] != value {
+                        continue;
+                    }
+                    designated[cell] = true;
+                    field[cell] = value;
+                }
+
+                let sample = ConvChainSample::new(field, size as u32, size as u32);
+                let mut components = Components::new(&sample, value, connectivity, boundary);
+                let mut designations = vec![0; components.len()];
+                for cell in (0..field.len()).filter(|&cell| designated[cell]) {
+                    designations
+                        [components.labels[cell].expect("designated elements have the value")] += 1;
+                }
+                if designations.iter().any(|&count| count > 1) {
+                    for (cell, label) in components.labels.iter().enumerate() {
+                        let merged = label.is_some_and(|label| designations[label] > 1);
+                        if merged && !designated[cell] && !is_fixed(cell) {
+                            field[cell] = !value;
+                        }
+                    }
+                    let sample = ConvChainSample::new(field, size as u32, size as u32);
+                    components = Components::new(&sample, value, connectivity, boundary);
+                }
+
+                // Every region starts out as a tree of depth one below its first element.
+                let mut roots = vec![None; components.len()];
+                let mut parents: Vec<usize> = (0..field.len()).collect();
+                for (cell, parent) in parents.iter_mut().enumerate() {
+                    if let Some(label) = components.labels[cell] {
+                        *parent = *roots[label].get_or_insert(cell);
+                    }
+                }
+                let mut seeded = vec![false; field.len()];
+                for cell in (0..field.len()).filter(|&cell| designated[cell]) {
+                    seeded[parents[cell]] = true;
+                }
+
+                separated = Some(Separated {
+                    parents,
+                    seeded,
+                    designated,
+                });
+            }
+        }
+
+        Self {
+            value,
+            connectivity,
+            size,
+            boundary,
+            count: field.iter().filter(|&&v| v == value).count(),
+            separated,
+            visited: vec![0; field.len()],
+            stamp: 0,
+            owners: vec![0; field.len()],
+            queues: Vec::new(),
+        }
+    }
+
+    /// Determines whether flipping the element keeps the constraint satisfied.
+    pub fn allows(&mut self, field: &[bool], r: usize) -> bool {
+        let adding = field[r] != self.value;
+        if let Some(separated) = &mut self.separated {
+            if !adding {
+                return !separated.designated[r];
+            }
+
+            let mut seeded_root = None;
+            for neighbor in
+                self.connectivity
+                    .neighbors(r, self.size as u32, self.size as u32, self.boundary)
+            {
+                if field[neighbor] != self.value {
+                    continue;
+                }
+                let root = separated.find(neighbor);
+                if separated.seeded[root] && seeded_root.replace(root).is_some_and(|o| o != root) {
+                    return false;
+                }
+            }
+            return true;
+        }
+
+        if adding {
+            // A new element must join the existing region.
+            self.count == 0
+                || self
+                    .connectivity
+                    .neighbors(r, self.size as u32, self.size as u32, self.boundary)
+                    .any(|neighbor| field[neighbor] == self.value)
+        } else {
+            self.stays_connected(field, r)
+        }
+    }
+
+    /// Updates the regions before the element is flipped.
+    pub fn flip(&mut self, field: &[bool], r: usize) {
+        if field[r] == self.value {
+            self.count -= 1;
+            return;
+        }
+        self.count += 1;
+
+        let value = self.value;
+        if let Some(separated) = &mut self.separated {
+            let neighbors =
+                self.connectivity
+                    .neighbors(r, self.size as u32, self.size as u32, self.boundary);
+            for neighbor in neighbors.filter(|&n| field[n] == value) {
+                separated.union(r, neighbor);
+            }
+        }
+    }
+
+    /// Determines whether the region stays connected without the element `r`.
+    fn stays_connected(&mut self, field: &[bool], r: usize) -> bool {
+        let mut ring = [None; 8];
+        for (cell, &(dx, dy)) in ring.iter_mut().zip(&RING) {
+            *cell = self
+                .offset(r, dx, dy)
+                .filter(|&cell| field[cell] == self.value);
+        }
+        let eight = self.connectivity == Connectivity::Eight;
+
+        // Group the neighbors by the components of the ring around the element. Components
+        // are separated by gaps in the ring, so there are at most four of them.
+        let mut components = [usize::MAX; 8];
+        let (mut seeds, mut len) = ([0; 4], 0);
+        let mut stack = [0; 8];
+        for start in (0..8).filter(|&i| ring[i].is_some() && (eight || i % 2 == 1)) {
+            if components[start] != usize::MAX {
+                continue;
+            }
+            let component = len;
+            seeds[component] = ring[start].expect("neighbors have the value");
+            len += 1;
+            components[start] = component;
+            let (mut top, mut steps) = (1, [0; 4]);
+            stack[0] = start;
+            while top > 0 {
+                top -= 1;
+                let i = stack[top];
+                steps[..2].copy_from_slice(&[(i + 1) % 8, (i + 7) % 8]);
+                let count = if eight && i % 2 == 1 {
+                    steps[2..].copy_from_slice(&[(i + 2) % 8, (i + 6) % 8]);
+                    4
+                } else {
+                    2
+                };
+                for &j in &steps[..count] {
+                    if ring[j].is_some() && components[j] == usize::MAX {
+                        components[j] = component;
+                        stack[top] = j;
+                        top += 1;
+                    }
+                }
+            }
+        }
+        let seeds = &seeds[..len];
+
+        // Usually, the neighbors are connected within the ring.
+        if seeds.len() <= 1 {
+            return true;
+        }
+
+        // Otherwise, search the region from every component at once, advancing the searches
+        // in turns until they have all met or some of them are enclosed.
+        self.stamp += 1;
+        let mut groups = [0, 1, 2, 3];
+        self.queues.resize_with(seeds.len(), VecDeque::new);
+        for (search, &seed) in seeds.iter().enumerate() {
+            self.visited[seed] = self.stamp;
+            self.owners[seed] = search;
+            self.queues[search].clear();
+            self.queues[search].push_back(seed);
+        }
+
+        let mut merges = seeds.len() - 1;
+        loop {
+            for search in 0..seeds.len() {
+                let cell = match self.queues[search].pop_front() {
+                    Some(cell) => cell,
+                    None => {
+                        let group = find(&mut groups, search);
+                        let enclosed = (0..seeds.len())
+                            .filter(|&other| find(&mut groups, other) == group)
+                            .all(|other| self.queues[other].is_empty());
+                        if enclosed {
+                            return false;
+                        }
+                        continue;
+                    }
+                };
+
+                for neighbor in self.connectivity.neighbors(
+                    cell,
+                    self.size as u32,
+                    self.size as u32,
+                    self.boundary,
+                ) {
+                    if neighbor == r || field[neighbor] != self.value {
+                        continue;
+                    }
+                    if self.visited[neighbor] != self.stamp {
+                        self.visited[neighbor] = self.stamp;
+                        self.owners[neighbor] = search;
+                        self.queues[search].push_back(neighbor);
+                        continue;
+                    }
+
+                    let (a, b) = (
+                        find(&mut groups, self.owners[neighbor]),
+                        find(&mut groups, search),
+                    );
+                    if a != b {
+                        groups[a] = b;
+                        merges -= 1;
+                        if merges == 0 {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The element at an offset from `cell`, if it lies within the field.
+    fn offset(&self, cell: usize, dx: i64, dy: i64) -> Option<usize> {
+        let size = self.size as i64;
+        let (x, y) = (cell as i64 % size + dx, cell as i64 / size + dy);
+        let x = match self.boundary.wraps_horizontally() {
+            true => x.rem_euclid(size),
+            false if (0..size).contains(&x) => x,
+            false => return None,
+        };
+        let y = match self.boundary.wraps_vertically() {
+            true => y.rem_euclid(size),
+            false if (0..size).contains(&y) => y,
+            false => return None,
+        };
+        Some((y * size + x) as usize)
+    }
+}
+
+/// Finds the root of an element of a small union-find forest.
+fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        i = parents[i];
+    }
+    i
+}
+
+impl Separated {
+    fn find(&mut self, mut cell: usize) -> usize {
+        while self.parents[cell] != cell {
+            self.parents[cell] = self.parents[self.parents[cell]];
+            cell = self.parents[cell];
+        }
+        cell
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parents[b] = a;
+            self.seeded[a] |= self.seeded[b];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::resource;
+    use crate::ConvChainModel;
+    use std::sync::Arc;
+
+    fn field(rows: &[&str]) -> ConvChainSample<'static> {
+        let rows = rows
+            .iter()
+            .map(|row| row.chars().map(|c| c == '#').collect())
+            .collect();
+        ConvChainSample::from_rows(rows).unwrap()
+    }
+
+    #[test]
+    fn regions_are_labeled_by_connectivity() {
+        let sample = field(&[
+            "##...", //
+            "##.#.", //
+            "....#", //
+            "#....", //
+        ]);
+
+        let four = Components::new(&sample, true, Connectivity::Four, Boundary::Open);
+        assert_eq!(four.sizes(), [4, 1, 1, 1]);
+        assert_eq!(four.label(1, 1), Some(0));
+        assert_eq!(four.label(2, 1), None);
+        let eight = Components::new(&sample, true, Connectivity::Eight, Boundary::Open);
+        assert_eq!(eight.sizes(), [4, 2, 1]);
+        let wrapped = Components::new(&sample, true, Connectivity::Four, Boundary::Periodic);
+        assert_eq!(wrapped.sizes(), [5, 1, 1]);
+
+        assert_eq!(
+            four.remove_islands(2),
+            field(&[
+                "##...", //
+                "##...", //
+                ".....", //
+                ".....", //
+            ])
+        );
+        assert_eq!(eight.keep_largest(), four.remove_islands(2));
+    }
+
+    #[test]
+    fn carving_connects_all_regions() {
+        let sample = field(&[
+            "##.....", //
+            "##.....", //
+            "......#", //
+            "#.....#", //
+        ]);
+        let components = Components::new(&sample, true, Connectivity::Four, Boundary::Open);
+        assert_eq!(components.len(), 3);
+
+        let carving = components.connect();
+        assert_eq!(carving.carved.len(), 1 + 5);
+        let connected = Components::new(&carving.field, true, Connectivity::Four, Boundary::Open);
+        assert_eq!(connected.len(), 1);
+    }
+
+    #[test]
+    fn relaxation_keeps_carved_paths() {
+        let sample = resource("Cave");
+        let model = Arc::new(ConvChainModel::new(&sample, 3));
+        let mut chain = ConvChain::from_model(model.clone(), 32, 1.0).with_seed(3);
+        chain.process(10);
+
+        let components = Components::new(
+            &chain.as_sample(),
+            false,
+            Connectivity::Four,
+            Boundary::Periodic,
+        );
+        assert!(components.len() > 1);
+        let carving = components.connect();
+        assert!(!carving.carved.is_empty());
+
+        let relaxed = carving.relax(ConvChain::from_model(model, 32, 1.0).with_seed(4), 2, 5);
+        assert!(carving.carved.iter().all(|&cell| !relaxed[cell]));
+        let changed = (0..32 * 32).filter(|&i| relaxed[i] != carving.field[i]);
+        assert!(changed.count() > 0);
+    }
+
+    fn chain(name: &str, seed: u64) -> ConvChain {
+        let model = Arc::new(ConvChainModel::new(&resource(name), 3));
+        ConvChain::from_model(model, 32, 1.0).with_seed(seed)
+    }
+
+    #[test]
+    fn constrained_floors_stay_connected() {
+        let mut chain = chain("Maze", 5).with_constraint(ConnectivityConstraint::Connected {
+            value: false,
+            connectivity: Connectivity::Four,
+        });
+        let trace = chain.process_traced(10, None);
+        assert!(trace.last().unwrap().unseen_fraction < 0.5 * trace[0].unseen_fraction);
+
+        let components = Components::new(
+            &chain.as_sample(),
+            false,
+            Connectivity::Four,
+            Boundary::Periodic,
+        );
+        assert_eq!(components.len(), 1);
+    }
+
+    #[test]
+    fn separated_regions_are_never_merged() {
+        let elements = vec![(4, 4), (20, 20)];
+        let mut chain = chain("Cave", 6).with_constraint(ConnectivityConstraint::Separated {
+            value: false,
+            connectivity: Connectivity::Four,
+            elements: elements.clone(),
+        });
+        for _ in 0..10 {
+            chain.process(1);
+            let components = Components::new(
+                &chain.as_sample(),
+                false,
+                Connectivity::Four,
+                Boundary::Periodic,
+            );
+            let (a, b) = (elements[0], elements[1]);
+            assert!(components.label(a.0, a.1).is_some());
+            assert_ne!(components.label(a.0, a.1), components.label(b.0, b.1));
+        }
+    }
+
+    #[test]
+    fn constraints_keep_fixed_elements() {
+        // A fixed wall enclosing a pocket, and a fixed corridor of floor outside of it.
+        let mut fixed = vec![false; 32 * 32];
+        let mut values = vec![false; 32 * 32];
+        for i in 0..10 {
+            for &(x, y) in &[(8 + i, 8), (8 + i, 17), (8, 8 + i), (17, 8 + i)] {
+                fixed[y * 32 + x] = true;
+                values[y * 32 + x] = true;
+            }
+            fixed[24 * 32 + 4 + i] = true;
+        }
+        let constraints = [
+            ConnectivityConstraint::Connected {
+                value: false,
+                connectivity: Connectivity::Four,
+            },
+            ConnectivityConstraint::Separated {
+                value: false,
+                connectivity: Connectivity::Four,
+                elements: vec![(2, 2), (12, 12)],
+            },
+        ];
+
+        for constraint in &constraints {
+            let chain = chain("Maze", 7);
+            let field: Vec<_> = (0..32 * 32)
+                .map(|i| {
+                    if fixed[i] {
+                        values[i]
+                    } else {
+                        chain.field()[i]
+                    }
+                })
+                .collect();
+            let mut chain = chain
+                .with_field(&ConvChainSample::new(&field, 32, 32))
+                .with_fixed(&ConvChainSample::new(&fixed, 32, 32))
+                .with_constraint(constraint.clone());
+            chain.process(3);
+            assert!((0..32 * 32).all(|i| !fixed[i] || chain.field()[i] == values[i]));
+
+            let components = Components::new(
+                &chain.as_sample(),
+                false,
+                Connectivity::Four,
+                Boundary::Periodic,
+            );
+            match constraint {
+                ConnectivityConstraint::Connected { .. } => assert_eq!(components.len(), 1),
+                ConnectivityConstraint::Separated { .. } => {
+                    assert_ne!(components.label(2, 2), components.label(12, 12))
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fixed_regions_are_left_alone() {
+        // A fixed floor tile inside a fixed wall, and a fixed corridor of floor outside of it.
+        let mut fixed = vec![false; 32 * 32];
+        let mut values = vec![false; 32 * 32];
+        for i in 0..10 {
+            for &(x, y) in &[(8 + i, 8), (8 + i, 17), (8, 8 + i), (17, 8 + i)] {
+                fixed[y * 32 + x] = true;
+                values[y * 32 + x] = true;
+            }
+            fixed[24 * 32 + 4 + i] = true;
+        }
+        fixed[12 * 32 + 12] = true;
+
+        let constraints = [
+            ConnectivityConstraint::Connected {
+                value: false,
+                connectivity: Connectivity::Four,
+            },
+            // The corridor connects the first two elements, and the last one is a wall.
+            ConnectivityConstraint::Separated {
+                value: false,
+                connectivity: Connectivity::Four,
+                elements: vec![(4, 24), (13, 24), (8, 8)],
+            },
+        ];
+        for constraint in &constraints {
+            let chain = chain("Maze", 8);
+            let field: Vec<_> = (0..32 * 32)
+                .map(|i| {
+                    if fixed[i] {
+                        values[i]
+                    } else {
+                        chain.field()[i]
+                    }
+                })
+                .collect();
+            let mut chain = chain
+                .with_field(&ConvChainSample::new(&field, 32, 32))
+                .with_fixed(&ConvChainSample::new(&fixed, 32, 32))
+                .with_constraint(constraint.clone());
+            chain.process(2);
+            assert!((0..32 * 32).all(|i| !fixed[i] || chain.field()[i] == values[i]));
+        }
+    }
+}
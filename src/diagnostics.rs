@@ -0,0 +1,100 @@
+/// Statistics of the field after an iteration of [`ConvChain::process_traced`](crate::ConvChain::process_traced).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SweepStatistics {
+    /// The zero-based index of the iteration.
+    pub sweep: usize,
+    /// The fraction of proposed flips that were accepted.
+    pub acceptance_rate: f64,
+    /// The sum of the log weights of all patterns in the field; higher is more sample-like.
+    pub energy: f64,
+    /// The fraction of patterns in the field that never occur in the sample.
+    pub unseen_fraction: f64,
+    /// The fraction of set field elements.
+    pub density: f64,
+}
+
+/// Detects convergence when the energy stops changing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlateauDetector {
+    /// The number of most recent iterations to consider.
+    pub window: usize,
+    /// The largest spread of the energies within the window, relative to their mean magnitude,
+    /// that is considered a plateau.
+    pub tolerance: f64,
+}
+
+impl Default for PlateauDetector {
+    fn default() -> Self {
+        Self {
+            window: 5,
+            tolerance: 0.01,
+        }
+    }
+}
+
+impl PlateauDetector {
+    pub fn new(window: usize, tolerance: f64) -> Self {
+        assert!(window > 1);
+        Self { window, tolerance }
+    }
+
+    pub fn is_converged(&self, trace: &[SweepStatistics]) -> bool {
+        if trace.len() < self.window {
+            return false;
+        }
+
+        let window = &trace[trace.len() - self.window..];
+        let (min, max, sum) = window.iter().fold(
+            (f64::INFINITY, f64::NEG_INFINITY, 0.),
+            |(min, max, sum), s| (min.min(s.energy), max.max(s.energy), sum + s.energy),
+        );
+        let mean = sum / window.len() as f64;
+        max - min <= self.tolerance * mean.abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::SIMPLE_MAZE;
+    use crate::{ConvChain, ConvChainModel, ConvChainSample};
+    use std::sync::Arc;
+
+    fn statistics(energy: f64) -> SweepStatistics {
+        SweepStatistics {
+            sweep: 0,
+            acceptance_rate: 0.,
+            energy,
+            unseen_fraction: 0.,
+            density: 0.,
+        }
+    }
+
+    #[test]
+    fn detects_plateau_within_window() {
+        let detector = PlateauDetector::new(3, 0.01);
+        let trace: Vec<_> = [10., 50., 99.5, 100., 100.2]
+            .iter()
+            .map(|&e| statistics(e))
+            .collect();
+
+        assert!(!detector.is_converged(&trace[..2]));
+        assert!(!detector.is_converged(&trace[..4]));
+        assert!(detector.is_converged(&trace));
+    }
+
+    #[test]
+    fn traced_processing_stops_on_convergence() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let model = Arc::new(ConvChainModel::new(&sample, 2));
+        let mut chain = ConvChain::from_model(model, 32, 1.0).with_seed(3);
+
+        let detector = PlateauDetector::new(3, 0.05);
+        let trace = chain.process_traced(100, Some(&detector));
+
+        assert!(trace.len() < 100);
+        assert!(detector.is_converged(&trace));
+        assert!(trace.last().unwrap().energy > trace[0].energy);
+        assert!(trace.last().unwrap().unseen_fraction <= trace[0].unseen_fraction);
+    }
+}
@@ -0,0 +1,218 @@
+use crate::hex::for_each_hex_pattern;
+use crate::model::for_each_pattern_variant;
+use crate::{
+    AsciiMapping, Boundary, ConvChainModel, ConvChainSample, HexLayout, HexSymmetry, Receptor,
+    Symmetry, SymmetryVariant,
+};
+use std::fmt::Write;
+
+/// A pattern a model has seen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternEntry {
+    /// The index of the pattern in the model's weight table.
+    pub index: usize,
+    /// The pattern within the receptor's bounding box.
+    pub pattern: ConvChainSample<'static>,
+    /// The number of occurrences in the sample, including symmetric variants,
+    /// or zero if no sample was [added](PatternDictionary::with_sample).
+    pub count: usize,
+    /// The pattern's weight in the model.
+    pub weight: f64,
+    /// The variants of sample patterns that map onto this pattern, or none if no sample was
+    /// [added](PatternDictionary::with_sample) or the model is a [hex model](ConvChainModel::hex).
+    pub variants: Vec<SymmetryVariant>,
+}
+
+/// The patterns a model has seen, most frequent first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternDictionary {
+    receptor: Receptor,
+    symmetry: Symmetry,
+    hex: Option<(HexLayout, HexSymmetry)>,
+    entries: Vec<PatternEntry>,
+}
+
+impl PatternDictionary {
+    /// Collects the patterns the model has seen, by descending weight.
+    pub fn new(model: &ConvChainModel) -> Self {
+        let receptor = model.receptor();
+        let entries = (0..model.weights().len())
+            .filter(|&index| model.has_seen(index))
+            .map(|index| PatternEntry {
+                index,
+                pattern: ConvChainSample::from_vec(
+                    receptor.decode(index),
+                    receptor.width(),
+                    receptor.height(),
+                )
+                .expect("decoded patterns have the receptor's size"),
+                count: 0,
+                weight: model.weights()[index],
+                variants: Vec::new(),
+            })
+            .collect();
+
+        let mut dictionary = Self {
+            receptor: receptor.clone(),
+            symmetry: model.symmetry(),
+            hex: model.hex(),
+            entries,
+        };
+        dictionary.sort();
+        dictionary
+    }
+
+    /// Counts the occurrences and symmetric variants of the patterns in a sample, e.g. the one
+    /// the model was learned from with [`Boundary::Periodic`], and sorts the patterns by
+    /// descending count. Patterns of the sample that the model has not seen are ignored.
+    ///
+    /// The patterns of hex models are counted in the layout and with the hexagonal symmetry
+    /// the model was learned with; their variants are not recorded.
+    pub fn with_sample(mut self, sample: &ConvChainSample, boundary: Boundary) -> Self {
+        let mut positions = vec![None; self.receptor.pattern_count()];
+        for (position, entry) in self.entries.iter_mut().enumerate() {
+            positions[entry.index] = Some(position);
+            entry.count = 0;
+            entry.variants.clear();
+        }
+
+        let entries = &mut self.entries;
+        let mut count = |index: usize, variant: Option<SymmetryVariant>| {
+            if let Some(entry) = positions[index].map(|position| &mut entries[position]) {
+                entry.count += 1;
+                if let Some(variant) = variant.filter(|v| !entry.variants.contains(v)) {
+                    entry.variants.push(variant);
+                }
+            }
+        };
+        match self.hex {
+            Some((layout, symmetry)) => for_each_hex_pattern(
+                sample,
+                layout,
+                &self.receptor,
+                symmetry,
+                boundary,
+                |index| count(index, None),
+            ),
+            None => for_each_pattern_variant(
+                sample,
+                &self.receptor,
+                self.symmetry,
+                boundary,
+                |_, _, variant, index| count(index, Some(variant)),
+            ),
+        }
+        self.sort();
+        self
+    }
+
+    fn sort(&mut self) {
+        self.entries.sort_by(|a, b| {
+            (b.count.cmp(&a.count))
+                .then(b.weight.total_cmp(&a.weight))
+                .then(a.index.cmp(&b.index))
+        });
+    }
+
+    #[inline]
+    pub fn receptor(&self) -> &Receptor {
+        &self.receptor
+    }
+
+    #[inline]
+    pub fn entries(&self) -> &[PatternEntry] {
+        &self.entries
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, PatternEntry> {
+        self.entries.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Lists every pattern as ASCII art below a line with its index, count, weight and variants.
+    pub fn to_text(&self, mapping: &AsciiMapping) -> String {
+        let mut text = String::new();
+        for entry in &self.entries {
+            let variants: Vec<_> = entry.variants.iter().map(|v| v.to_string()).collect();
+            writeln!(
+                text,
+                "#{} count={} weight={} variants={}",
+                entry.index,
+                entry.count,
+                entry.weight,
+                variants.join("; ")
+            )
+            .expect("writing to a string cannot fail");
+            text.push_str(&entry.pattern.to_ascii(mapping));
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl<'a> IntoIterator for &'a PatternDictionary {
+    type Item = &'a PatternEntry;
+    type IntoIter = std::slice::Iter<'a, PatternEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_reconstruct_the_sample_patterns() {
+        let sample =
+            ConvChainSample::from_ascii("##..\n##..\n....\n....", &AsciiMapping::default())
+                .unwrap();
+        let model = ConvChainModel::learn(&sample, 2, Symmetry::Rotation, 0.1);
+        let dictionary = PatternDictionary::new(&model).with_sample(&sample, Boundary::Periodic);
+
+        // Besides the empty and the full pattern, there are the four rotations each
+        // of a corner and a half pattern.
+        assert_eq!(dictionary.len(), 2 + 2 * 4);
+        assert_eq!(dictionary.entries()[0].index, 0);
+        assert_eq!(dictionary.entries()[0].count, 7 * 4);
+        assert_eq!(dictionary.iter().map(|e| e.count).sum::<usize>(), 16 * 4);
+
+        let full = dictionary.iter().find(|e| e.index == 0b1111).unwrap();
+        assert_eq!(full.pattern.as_slice(), [true; 4]);
+        assert_eq!(full.weight, model.weights()[0b1111]);
+        assert_eq!(full.variants.len(), 4);
+
+        let corner = dictionary.iter().find(|e| e.index == 0b0001).unwrap();
+        assert_eq!(corner.pattern.as_slice(), [true, false, false, false]);
+        assert!(corner.variants.contains(&SymmetryVariant::Identity));
+    }
+
+    #[test]
+    fn entries_follow_the_model() {
+        let sample = ConvChainSample::from_ascii("##..\n##..", &AsciiMapping::default()).unwrap();
+        let mut model = ConvChainModel::learn(&sample, 2, Symmetry::None, 0.1);
+        let dictionary = PatternDictionary::new(&model);
+        assert_eq!(dictionary.len(), 4);
+        assert!(dictionary
+            .iter()
+            .all(|e| e.count == 0 && e.variants.is_empty()));
+        assert!(dictionary.iter().all(|e| model.has_seen(e.index)));
+
+        // The sample is counted with the specified boundary.
+        let dictionary = dictionary.with_sample(&sample, Boundary::Open);
+        assert_eq!(dictionary.iter().map(|e| e.count).sum::<usize>(), 3);
+
+        model.weights_mut()[0b1111] = 10.;
+        assert_eq!(PatternDictionary::new(&model).entries()[0].weight, 10.);
+    }
+}
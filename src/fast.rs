@@ -1,23 +1,106 @@
-use crate::ConvChainSample;
+use crate::connectivity::ConstraintState;
+use crate::guide::GuideState;
+use crate::{
+    ConnectivityConstraint, ConvChainModel, ConvChainSample, Guide, Interpolation, PlateauDetector,
+    Receptor, SweepStatistics,
+};
 use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::sync::Arc;
 
 pub struct ConvChain {
-    receptor_size: u32,
+    /// The models whose energies add up; the first one may be blended.
+    models: Vec<Arc<ConvChainModel>>,
     one_over_temperature: f64,
     output_size: usize,
+    boundary: Boundary,
     field: Vec<bool>,
-    weights: Vec<f64>,
+    blend: Option<SpatialBlend>,
+    guide: Option<Guide>,
+    /// The elements that are never flipped.
+    fixed: Option<Vec<bool>>,
+    constraint: Option<ConnectivityConstraint>,
+    rng: StdRng,
+}
+
+/// How receptors are treated at the edges of the field.
+///
+/// Along axes where the field wraps around, the output tiles seamlessly; along the others,
+/// only receptors lying completely within the field are considered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Boundary {
+    /// The field wraps around at all edges, which makes the output tileable in both directions.
+    #[default]
+    Periodic,
+    /// Only receptors lying completely within the field are considered.
+    Open,
+    /// The field wraps around at its left and right edges, e.g. for side-scroller strips.
+    Horizontal,
+    /// The field wraps around at its top and bottom edges.
+    Vertical,
+}
+
+impl Boundary {
+    /// Creates the boundary that wraps around along the specified axes.
+    pub fn from_axes(horizontal: bool, vertical: bool) -> Self {
+        match (horizontal, vertical) {
+            (true, true) => Boundary::Periodic,
+            (true, false) => Boundary::Horizontal,
+            (false, true) => Boundary::Vertical,
+            (false, false) => Boundary::Open,
+        }
+    }
+
+    /// Whether the field wraps around at its left and right edges.
+    #[inline]
+    pub fn wraps_horizontally(self) -> bool {
+        matches!(self, Boundary::Periodic | Boundary::Horizontal)
+    }
+
+    /// Whether the field wraps around at its top and bottom edges.
+    #[inline]
+    pub fn wraps_vertically(self) -> bool {
+        matches!(self, Boundary::Periodic | Boundary::Vertical)
+    }
+
+    /// The boundary of the field with its axes swapped, e.g. after rotating it by 90°.
+    pub(crate) fn transposed(self) -> Self {
+        Self::from_axes(self.wraps_vertically(), self.wraps_horizontally())
+    }
+}
+
+/// A second model that is blended into the chain's first model with a per-cell factor.
+struct SpatialBlend {
+    model: Arc<ConvChainModel>,
+    alpha: Vec<f64>,
+    interpolation: Interpolation,
 }
 
 impl ConvChain {
     pub fn new(
         sample: &ConvChainSample,
         output_size: u32,
-        receptor_size: u32,
+        receptor: impl Into<Receptor>,
+        temperature: f64,
+    ) -> Self {
+        let model = ConvChainModel::new(sample, receptor);
+        Self::from_model(Arc::new(model), output_size, temperature)
+    }
+
+    pub fn from_model(model: Arc<ConvChainModel>, output_size: u32, temperature: f64) -> Self {
+        Self::from_models(vec![model], output_size, temperature)
+    }
+
+    /// Creates a chain whose energy is the sum of the energies under all models, e.g. models
+    /// of differently shaped receptors learned from the same sample.
+    pub fn from_models(
+        models: Vec<Arc<ConvChainModel>>,
+        output_size: u32,
         temperature: f64,
     ) -> Self {
-        let weights = Self::initialize_weights(&sample, receptor_size);
-        let field = Self::initialize_field(output_size);
+        assert!(!models.is_empty(), "the chain requires a model");
+        let mut rng = StdRng::from_entropy();
+        let field = Self::initialize_field(output_size, &mut rng);
 
         let one_over_temperature = if temperature != 1.0 {
             1.0 / temperature
@@ -26,184 +109,478 @@ impl ConvChain {
         };
 
         Self {
-            receptor_size,
+            models,
             one_over_temperature,
             output_size: output_size as usize,
+            boundary: Boundary::default(),
             field,
-            weights,
+            blend: None,
+            guide: None,
+            fixed: None,
+            constraint: None,
+            rng,
         }
     }
 
+    /// Seeds the chain's random number generator and re-initializes the field from it,
+    /// making the results reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.field = Self::initialize_field(self.output_size as u32, &mut self.rng);
+        self
+    }
+
+    pub fn with_boundary(mut self, boundary: Boundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    /// Starts the chain from the specified field instead of random noise, e.g. an upsampled
+    /// coarser result. Since [`Self::with_seed`] re-initializes the field, it must be called first.
+    pub fn with_field(mut self, field: &ConvChainSample) -> Self {
+        assert_eq!(
+            (field.width, field.height),
+            (self.output_size as u32, self.output_size as u32),
+            "the field must have the output size"
+        );
+        self.field = field.as_slice().to_vec();
+        self
+    }
+
+    /// Keeps the elements that are set in the mask at their current values, e.g. context
+    /// surrounding a region to be filled in. Call it after [`Self::with_field`].
+    pub fn with_fixed(mut self, mask: &ConvChainSample) -> Self {
+        assert_eq!(
+            (mask.width, mask.height),
+            (self.output_size as u32, self.output_size as u32),
+            "the mask must have the output size"
+        );
+        self.fixed = Some(mask.as_slice().to_vec());
+        self
+    }
+
+    /// Rejects all flips that would violate the connectivity constraint.
+    pub fn with_constraint(mut self, constraint: ConnectivityConstraint) -> Self {
+        self.constraint = Some(constraint);
+        self
+    }
+
+    /// Makes the local density of the field follow a low-resolution guide.
+    pub fn with_guide(mut self, guide: Guide) -> Self {
+        self.guide = Some(guide);
+        self
+    }
+
+    /// Creates a chain whose weights are blended between the models `a` and `b`
+    /// using the blend factor of each receptor's origin cell in the row-major `alpha` map.
+    pub fn from_blend(
+        a: Arc<ConvChainModel>,
+        b: Arc<ConvChainModel>,
+        alpha: Vec<f64>,
+        interpolation: Interpolation,
+        output_size: u32,
+        temperature: f64,
+    ) -> Self {
+        assert_eq!(
+            a.receptor(),
+            b.receptor(),
+            "blended models must use the same receptor"
+        );
+        assert_eq!(alpha.len(), output_size as usize * output_size as usize);
+        assert!(alpha.iter().all(|alpha| (0. ..=1.).contains(alpha)));
+
+        let mut chain = Self::from_model(a, output_size, temperature);
+        chain.blend = Some(SpatialBlend {
+            model: b,
+            alpha,
+            interpolation,
+        });
+        chain
+    }
+
+    /// Replaces the first model while keeping the current field, e.g. for continuing the chain
+    /// with adjusted weights.
+    pub(crate) fn set_model(&mut self, model: Arc<ConvChainModel>) {
+        assert_eq!(model.receptor(), self.models[0].receptor());
+        self.models[0] = model;
+    }
+
+    /// The first of the chain's models.
+    #[inline]
+    pub fn model(&self) -> &Arc<ConvChainModel> {
+        &self.models[0]
+    }
+
+    #[inline]
+    pub fn models(&self) -> &[Arc<ConvChainModel>] {
+        &self.models
+    }
+
+    #[inline]
+    pub fn output_size(&self) -> u32 {
+        self.output_size as u32
+    }
+
+    #[inline]
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    #[inline]
+    pub fn field(&self) -> &[bool] {
+        &self.field
+    }
+
+    /// Provides a view of the current field as a sample, e.g. for rendering it
+    /// or for learning a model from it.
+    pub fn as_sample(&self) -> ConvChainSample<'_> {
+        ConvChainSample::new(&self.field, self.output_size(), self.output_size())
+    }
+
     pub fn process(&mut self, iterations: usize) -> &[bool] {
-        let mut rng = rand::thread_rng();
+        let mut states = self.states();
+        for _ in 0..iterations {
+            self.sweep(&mut states);
+        }
+
+        &self.field
+    }
+
+    /// Runs up to `max_iterations` iterations, recording statistics after every iteration.
+    /// If a `detector` is specified, processing stops as soon as it reports convergence.
+    pub fn process_traced(
+        &mut self,
+        max_iterations: usize,
+        detector: Option<&PlateauDetector>,
+    ) -> Vec<SweepStatistics> {
+        let mut trace = Vec::with_capacity(max_iterations);
+        let mut states = self.states();
+        for sweep in 0..max_iterations {
+            let accepted = self.sweep(&mut states);
+            trace.push(self.statistics(sweep, accepted));
+
+            if detector.is_some_and(|d| d.is_converged(&trace)) {
+                break;
+            }
+        }
+        trace
+    }
+
+    /// Prepares the constraint and the guide for processing; both are then updated with every
+    /// accepted flip.
+    fn states(&mut self) -> (Option<ConstraintState>, Option<GuideState>) {
+        let (field, size, boundary) = (&mut self.field, self.output_size, self.boundary);
+        let fixed = self.fixed.as_deref();
+        let constraint = self
+            .constraint
+            .as_ref()
+            .map(|constraint| ConstraintState::new(constraint, field, fixed, size, boundary));
+        let guide = self
+            .guide
+            .as_ref()
+            .map(|guide| GuideState::new(guide, &self.field, self.output_size, self.boundary));
+        (constraint, guide)
+    }
+
+    /// Performs one Metropolis step per field element and returns the number of accepted flips.
+    fn sweep(&mut self, states: &mut (Option<ConstraintState>, Option<GuideState>)) -> usize {
         let num_field_elements = self.output_size * self.output_size;
-        let num_loops = iterations * num_field_elements;
-        for _ in 0..num_loops {
-            let r = rng.gen_range(0..num_field_elements) as usize;
+        let (constraint, guide) = states;
+
+        let mut accepted = 0;
+        for _ in 0..num_field_elements {
+            let r = self.rng.gen_range(0..num_field_elements);
+            if self.fixed.as_ref().is_some_and(|fixed| fixed[r]) {
+                continue;
+            }
 
             let mut q = self.single_iteration(r);
+            if let Some(guide) = guide {
+                q *= (-guide.energy_change(r, self.field[r])).exp();
+            }
 
             // Metropolis algorithm: If q is greater than or equal to 1, always accept.
-            if q >= 1. {
-                self.field[r] = !self.field[r];
+            // If q is less than 1, accept with a probability.
+            let accept = q >= 1. || {
+                if self.one_over_temperature != 1. {
+                    q = q.powf(self.one_over_temperature);
+                }
+                q > self.rng.gen()
+            };
+
+            if !accept {
                 continue;
             }
 
-            // Metropolis algorithm: If q is less than 1, accept with a probability.
-            if self.one_over_temperature != 1. {
-                q = q.powf(self.one_over_temperature);
+            // Flips violating the constraint are rejected; checking them last is cheapest.
+            if let Some(constraint) = constraint.as_mut() {
+                if !constraint.allows(&self.field, r) {
+                    continue;
+                }
+                constraint.flip(&self.field, r);
             }
-            if q > rng.gen() {
-                self.field[r] = !self.field[r];
+            if let Some(guide) = guide.as_mut() {
+                guide.flip(r, self.field[r]);
             }
+            self.field[r] = !self.field[r];
+            accepted += 1;
         }
 
-        &self.field
+        accepted
     }
 
+    fn statistics(&self, sweep: usize, accepted: usize) -> SweepStatistics {
+        let num_field_elements = self.field.len();
+        let mut energy = 0.;
+        let mut unseen = 0usize;
+        let mut patterns = 0usize;
+        for model in 0..self.models.len() {
+            let (max_x, max_y) = self.last_origin(model);
+            for sy in 0..=max_y {
+                for sx in 0..=max_x {
+                    let index = self.pattern_index(model, sx, sy, None);
+                    energy += self.weight(model, sx, sy, index).ln();
+                    if !self.has_seen(model, index) {
+                        unseen += 1;
+                    }
+                    patterns += 1;
+                }
+            }
+        }
+
+        SweepStatistics {
+            sweep,
+            acceptance_rate: accepted as f64 / num_field_elements as f64,
+            energy,
+            unseen_fraction: if patterns > 0 {
+                unseen as f64 / patterns as f64
+            } else {
+                0.
+            },
+            density: self.field.iter().filter(|&&v| v).count() as f64 / num_field_elements as f64,
+        }
+    }
+
+    /// Determines the ratio of the weights of all patterns covering the element
+    /// after and before flipping it.
     fn single_iteration(&self, r: usize) -> f64 {
         let out_y = r / self.output_size;
         let out_x = r % self.output_size;
 
-        let sy_min = out_y as i64 - self.receptor_size as i64 + 1;
-        let sy_max = out_y as i64 + self.receptor_size as i64 - 1;
-        let sx_min = out_x as i64 - self.receptor_size as i64 + 1;
-        let sx_max = out_x as i64 + self.receptor_size as i64 - 1;
-
         let mut q: f64 = 1.0;
+        for model in 0..self.models.len() {
+            let receptor = self.models[model].receptor();
+            let mut sy_min = out_y as i64 - receptor.height() as i64 + 1;
+            let mut sx_min = out_x as i64 - receptor.width() as i64 + 1;
+            let mut sy_max = out_y as i64;
+            let mut sx_max = out_x as i64;
+
+            let (last_x, last_y) = self.last_origin(model);
+            if !self.boundary.wraps_horizontally() {
+                sx_min = sx_min.max(0);
+                sx_max = sx_max.min(last_x);
+            }
+            if !self.boundary.wraps_vertically() {
+                sy_min = sy_min.max(0);
+                sy_max = sy_max.min(last_y);
+            }
 
-        for sy in sy_min..=sy_max {
-            for sx in sx_min..=sx_max {
-                let weight = self.iteration_inner_loop(out_x, out_y, sx, sy);
-                q *= weight;
+            for sy in sy_min..=sy_max {
+                for sx in sx_min..=sx_max {
+                    q *= self.iteration_inner_loop(model, r, sx, sy);
+                }
             }
         }
 
         q
     }
 
-    fn iteration_inner_loop(&self, out_x: usize, out_y: usize, sx: i64, sy: i64) -> f64 {
-        let mut weight_index = 0;
-        let mut difference: i64 = 0;
+    fn iteration_inner_loop(&self, model: usize, r: usize, sx: i64, sy: i64) -> f64 {
+        let old_index = self.pattern_index(model, sx, sy, None);
+        let new_index = self.pattern_index(model, sx, sy, Some(r));
 
-        let field = &self.field;
-
-        for dy in 0..self.receptor_size {
-            let local_y = self.get_local_coordinate(sy, dy);
-            let local_row = local_y * self.output_size;
-            let is_relevant_row = out_y == local_y;
+        // The receptor does not cover the changed cell.
+        if new_index == old_index {
+            return 1.;
+        }
 
-            for dx in 0..self.receptor_size {
-                let power = 1i64 << (dy * self.receptor_size + dx);
+        // Metropolis algorithm: Determine energy difference before and after change.
+        self.weight(model, sx, sy, new_index) / self.weight(model, sx, sy, old_index)
+    }
 
-                let local_x = self.get_local_coordinate(sx, dx);
-                let is_relevant_column = out_x == local_x;
+    /// The largest receptor origin of a model covered by the statistics.
+    fn last_origin(&self, model: usize) -> (i64, i64) {
+        let receptor = self.models[model].receptor();
+        let last = self.output_size as i64 - 1;
+        let last_x = if self.boundary.wraps_horizontally() {
+            last
+        } else {
+            last + 1 - receptor.width() as i64
+        };
+        let last_y = if self.boundary.wraps_vertically() {
+            last
+        } else {
+            last + 1 - receptor.height() as i64
+        };
+        (last_x, last_y)
+    }
 
-                let value = self.field[local_row + local_x];
-                if value {
-                    weight_index += power;
-                }
+    /// Determines the weight table index of the pattern at the specified origin,
+    /// optionally with the element `flipped` inverted.
+    fn pattern_index(&self, model: usize, sx: i64, sy: i64, flipped: Option<usize>) -> usize {
+        self.models[model].receptor().index(|dx, dy| {
+            let cell = self.get_local_coordinate(sy, dy) * self.output_size
+                + self.get_local_coordinate(sx, dx);
+            self.field[cell] != (flipped == Some(cell))
+        })
+    }
 
-                if is_relevant_row && is_relevant_column {
-                    difference = if value { power } else { -power };
-                }
+    /// Determines the weight of a pattern at the specified origin.
+    fn weight(&self, model: usize, sx: i64, sy: i64, index: usize) -> f64 {
+        let weight = self.models[model].weights()[index];
+        match &self.blend {
+            Some(blend) if model == 0 => {
+                let origin = self.get_local_coordinate(sy, 0) * self.output_size
+                    + self.get_local_coordinate(sx, 0);
+                let other = blend.model.weights()[index];
+                blend
+                    .interpolation
+                    .interpolate(weight, other, blend.alpha[origin])
             }
+            _ => weight,
         }
+    }
 
-        // Metropolis algorithm: Determine energy difference before and after change.
-        self.weights[(weight_index - difference) as usize] / self.weights[weight_index as usize]
+    fn has_seen(&self, model: usize, index: usize) -> bool {
+        self.models[model].has_seen(index)
+            || (model == 0
+                && self
+                    .blend
+                    .as_ref()
+                    .is_some_and(|blend| blend.model.has_seen(index)))
     }
 
     fn get_local_coordinate(&self, s: i64, d: u32) -> usize {
-        let mut local = s + d as i64;
-        if local < 0 {
-            local += self.output_size as i64;
-        } else if local >= self.output_size as i64 {
-            local -= self.output_size as i64;
-        }
-        local as usize
-    }
-
-    fn initialize_weights(sample: &ConvChainSample, receptor_size: u32) -> Vec<f64> {
-        // Initialize all values to 0.1.
-        // This deviates from the original code in that _every_ weight is offset
-        // by 0.1, but it saves an extra loop.
-        let mut weights = vec![0.1; 1 << (receptor_size * receptor_size)];
-
-        for y in 0..sample.height {
-            for x in 0..sample.width {
-                let mut ps = Vec::with_capacity(8);
-                let pattern = pattern(|dx, dy| sample[(x + dx, y + dy)], receptor_size);
-
-                ps.push(pattern);
-                ps.push(rotate(&ps[0], receptor_size));
-                ps.push(rotate(&ps[1], receptor_size));
-                ps.push(rotate(&ps[2], receptor_size));
-                ps.push(reflect(&ps[0], receptor_size));
-                ps.push(reflect(&ps[1], receptor_size));
-                ps.push(reflect(&ps[2], receptor_size));
-                ps.push(reflect(&ps[3], receptor_size));
-
-                for k in 0..8 {
-                    weights[index(&ps[k])] += 1.0;
-                }
-            }
-        }
-
-        weights
+        (s + d as i64).rem_euclid(self.output_size as i64) as usize
     }
 
-    fn initialize_field(output_size: u32) -> Vec<bool> {
-        let mut rng = rand::thread_rng();
-        let mut field = vec![false; output_size as usize * output_size as usize];
-        for i in 0..field.len() {
-            field[i as usize] = rng.gen();
-        }
-        field
+    fn initialize_field(output_size: u32, rng: &mut StdRng) -> Vec<bool> {
+        (0..output_size as usize * output_size as usize)
+            .map(|_| rng.gen())
+            .collect()
     }
 }
 
-fn pattern<F>(f: F, receptor_size: u32) -> Vec<bool>
-where
-    F: Fn(u32, u32) -> bool,
-{
-    let mut result = vec![false; receptor_size as usize * receptor_size as usize];
-    for y in 0..receptor_size {
-        let row_offset = (y * receptor_size) as usize;
-        for x in 0..receptor_size {
-            result[row_offset + x as usize] = f(x, y);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{resource, unseen_fraction, SIMPLE_MAZE};
+    use crate::Symmetry;
+
+    #[test]
+    fn seeded_chains_are_reproducible() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let model = Arc::new(ConvChainModel::new(&sample, 2));
+
+        let mut first = ConvChain::from_model(model.clone(), 16, 1.0).with_seed(42);
+        let mut second = ConvChain::from_model(model, 16, 1.0).with_seed(42);
+        assert_eq!(first.process(2), second.process(2));
     }
-    result
-}
 
-fn rotate(p: &[bool], receptor_size: u32) -> Vec<bool> {
-    pattern(
-        |x, y| {
-            let index = receptor_size - 1 - y + x * receptor_size;
-            p[index as usize]
-        },
-        receptor_size,
-    )
-}
+    #[test]
+    fn multiple_receptors_add_energies() {
+        // Horizontal stripes of height two: rows are uniform, columns alternate in pairs.
+        let stripes: Vec<_> = (0..64).map(|i| i / 8 % 4 < 2).collect();
+        let sample = ConvChainSample::new(&stripes, 8, 8);
+        let rows = Arc::new(ConvChainModel::learn(
+            &sample,
+            Receptor::rectangle(4, 1),
+            Symmetry::None,
+            0.1,
+        ));
+        let columns = Arc::new(ConvChainModel::learn(
+            &sample,
+            Receptor::rectangle(1, 4),
+            Symmetry::None,
+            0.1,
+        ));
+
+        let mut chain = ConvChain::from_models(vec![rows, columns], 16, 0.2).with_seed(5);
+        let initial = chain.statistics(0, 0);
+        let trace = chain.process_traced(30, None);
+        let last = trace.last().unwrap();
+        assert!(last.unseen_fraction < 0.1 * initial.unseen_fraction);
+        assert!(last.energy > initial.energy);
+    }
 
-fn reflect(p: &[bool], receptor_size: u32) -> Vec<bool> {
-    pattern(
-        |x, y| {
-            let index = receptor_size - 1 - x + y * receptor_size;
-            p[index as usize]
-        },
-        receptor_size,
-    )
-}
+    #[test]
+    fn blend_maps_select_models_spatially() {
+        // Horizontal stripes in the west, vertical stripes in the east.
+        let horizontal: Vec<_> = (0..64).map(|i| i / 8 % 4 < 2).collect();
+        let vertical: Vec<_> = (0..64).map(|i| i % 8 % 4 < 2).collect();
+        let horizontal = ConvChainSample::new(&horizontal, 8, 8);
+        let vertical = ConvChainSample::new(&vertical, 8, 8);
+        let learn = |sample| Arc::new(ConvChainModel::learn(sample, 3, Symmetry::None, 0.01));
+
+        let alpha = (0..32 * 32).map(|i| (i % 32) as f64 / 31.).collect();
+        let mut chain = ConvChain::from_blend(
+            learn(&horizontal),
+            learn(&vertical),
+            alpha,
+            Interpolation::Geometric,
+            32,
+            1.0,
+        )
+        .with_seed(3);
+        chain.process(20);
+
+        let field = chain.as_sample();
+        let half = |columns: std::ops::Range<u32>| {
+            let values = (0..32).flat_map(|y| columns.clone().map(move |x| (x, y)));
+            let values = values.map(|(x, y)| field[(x, y)]);
+            let sample = ConvChainSample::from_row_major(values, 16, 32).unwrap();
+            crate::PatternHistogram::from_field(&sample, 3, Boundary::Open)
+        };
+        let reference = |sample| crate::PatternHistogram::from_sample(sample, 3, Symmetry::None);
+        let distance = |half: &crate::PatternHistogram, sample| {
+            half.distance(&reference(sample)).total_variation
+        };
+
+        let (west, east) = (half(0..16), half(16..32));
+        assert!(distance(&west, &horizontal) < distance(&west, &vertical));
+        assert!(distance(&east, &vertical) < distance(&east, &horizontal));
+    }
+
+    #[test]
+    fn horizontal_boundaries_tile_horizontally() {
+        let model = Arc::new(ConvChainModel::new(&resource("Cave"), 3));
+        let mut chain = ConvChain::from_model(model.clone(), 24, 1.0)
+            .with_seed(4)
+            .with_boundary(Boundary::Horizontal);
+        chain.process(20);
+
+        // Repeating the field along each axis and counting the patterns within the repetition.
+        let field = chain.as_sample();
+        let repeated = |width: u32, height: u32| {
+            let values = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+            let values = values.map(|(x, y)| field[(x % 24, y % 24)]);
+            let sample = ConvChainSample::from_row_major(values, width, height).unwrap();
+            unseen_fraction(&model, &sample)
+        };
+        assert_eq!(repeated(48, 24), 0.);
+        assert!(repeated(24, 48) > 0.);
+    }
 
-fn index(p: &[bool]) -> usize {
-    let mut result = 0;
-    let mut power = 1;
-    let length = p.len();
-    for i in 0..length {
-        result += if p[length - 1 - i] { power } else { 0 };
-        power *= 2;
+    #[test]
+    fn dilated_receptors_capture_larger_structures() {
+        // A checkerboard of 2 × 2 blocks: cells two elements apart always differ.
+        let blocks: Vec<_> = (0..64).map(|i| (i % 8 / 2 + i / 16) % 2 == 0).collect();
+        let sample = ConvChainSample::new(&blocks, 8, 8);
+        let model = Arc::new(ConvChainModel::new(&sample, Receptor::dilated(2, 2, 2)));
+        assert_eq!(model.weights().iter().filter(|&&w| w > 0.1).count(), 2);
+
+        let mut chain = ConvChain::from_model(model, 16, 0.2).with_seed(9);
+        let trace = chain.process_traced(30, None);
+        assert!(trace.last().unwrap().unseen_fraction < 0.1);
     }
-    result
 }
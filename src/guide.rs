@@ -0,0 +1,234 @@
+use crate::{Boundary, ConvChainSample};
+
+/// A low-resolution density map, e.g. a designer's sketch, that the local density of a
+/// generated field should follow.
+///
+/// Guiding adds `strength · Σ (m - g)²` to the energy of a field, summing over all elements
+/// the squared deviation of the mean `m` of the surrounding window from the upsampled guide
+/// value `g`. The strength is in units of log weights.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guide {
+    values: Vec<f64>,
+    width: u32,
+    height: u32,
+    strength: f64,
+    radius: Option<u32>,
+}
+
+impl Guide {
+    /// Creates a guide from row-major densities in the range `0..=1`.
+    pub fn new(values: Vec<f64>, width: u32, height: u32, strength: f64) -> Self {
+        assert_eq!(values.len(), width as usize * height as usize);
+        assert!(values.iter().all(|v| (0. ..=1.).contains(v)));
+        assert!(strength >= 0.);
+        Self {
+            values,
+            width,
+            height,
+            strength,
+            radius: None,
+        }
+    }
+
+    /// Creates a guide that asks for set elements wherever the sample is set.
+    pub fn from_sample(sample: &ConvChainSample, strength: f64) -> Self {
+        let values = sample
+            .as_slice()
+            .iter()
+            .map(|&v| if v { 1. } else { 0. })
+            .collect();
+        Self::new(values, sample.width, sample.height, strength)
+    }
+
+    /// Sets the radius of the window around every element whose mean is compared to the guide.
+    /// By default, the window is about as large as a guide element in the output.
+    pub fn with_radius(mut self, radius: u32) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    #[inline]
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn strength(&self) -> f64 {
+        self.strength
+    }
+
+    /// Determines the window radius for an output of the specified size.
+    fn radius(&self, output_size: usize) -> usize {
+        match self.radius {
+            Some(radius) => radius as usize,
+            None => output_size / self.width.max(self.height) as usize / 2,
+        }
+    }
+}
+
+/// The window sums of a field being guided.
+pub(crate) struct GuideState {
+    /// The upsampled guide value of every element.
+    targets: Vec<f64>,
+    /// The number of set elements in the window around every element.
+    sums: Vec<u32>,
+    /// The number of elements in the window around every element.
+    counts: Vec<u32>,
+    strength: f64,
+    radius: i64,
+    size: i64,
+    boundary: Boundary,
+}
+
+impl GuideState {
+    pub fn new(guide: &Guide, field: &[bool], output_size: usize, boundary: Boundary) -> Self {
+        let size = output_size as i64;
+        let targets = (0..output_size * output_size)
+            .map(|i| {
+                let gx = (i % output_size) * guide.width as usize / output_size;
+                let gy = (i / output_size) * guide.height as usize / output_size;
+                guide.values[gy * guide.width as usize + gx]
+            })
+            .collect();
+
+        let mut state = Self {
+            targets,
+            sums: vec![0; field.len()],
+            counts: vec![0; field.len()],
+            strength: guide.strength,
+            radius: guide.radius(output_size) as i64,
+            size,
+            boundary,
+        };
+
+        for (cell, &value) in field.iter().enumerate() {
+            for center in state.windows(cell) {
+                state.counts[center] += 1;
+                state.sums[center] += value as u32;
+            }
+        }
+        state
+    }
+
+    /// Determines the change of the guide energy when flipping the element.
+    pub fn energy_change(&self, cell: usize, value: bool) -> f64 {
+        let delta = if value { -1. } else { 1. };
+        self.windows(cell)
+            .map(|center| {
+                let count = self.counts[center] as f64;
+                let deviation = self.sums[center] as f64 / count - self.targets[center];
+                delta / count * (2. * deviation + delta / count)
+            })
+            .sum::<f64>()
+            * self.strength
+    }
+
+    /// Updates the window sums after flipping the element, whose previous value is `value`.
+    pub fn flip(&mut self, cell: usize, value: bool) {
+        for center in self.windows(cell) {
+            if value {
+                self.sums[center] -= 1;
+            } else {
+                self.sums[center] += 1;
+            }
+        }
+    }
+
+    /// The centers of all windows containing the element.
+    fn windows(&self, cell: usize) -> impl Iterator<Item = usize> {
+        let (radius, size) = (self.radius, self.size);
+        let (wrap_x, wrap_y) = (
+            self.boundary.wraps_horizontally(),
+            self.boundary.wraps_vertically(),
+        );
+        let (x, y) = (cell as i64 % size, cell as i64 / size);
+        // Along wrapping axes, windows larger than the field count elements more than once.
+        let wrap = move |c: i64, wraps: bool| {
+            if wraps {
+                Some(c.rem_euclid(size))
+            } else {
+                Some(c).filter(|c| (0..size).contains(c))
+            }
+        };
+        let range = move |c: i64| c - radius..=c + radius;
+        range(y)
+            .filter_map(move |cy| wrap(cy, wrap_y))
+            .flat_map(move |cy| {
+                range(x)
+                    .filter_map(move |cx| wrap(cx, wrap_x))
+                    .map(move |cx| (cy * size + cx) as usize)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::resource;
+    use crate::{ConvChain, ConvChainModel};
+    use std::sync::Arc;
+
+    #[test]
+    fn energy_change_matches_recomputed_energy() {
+        let guide = Guide::new(vec![0.2, 0.9, 0.5, 0.], 2, 2, 3.).with_radius(1);
+        let mut field: Vec<bool> = (0..36).map(|i| i % 3 == 0 || i % 5 == 0).collect();
+
+        for &boundary in &[Boundary::Periodic, Boundary::Open, Boundary::Horizontal] {
+            let energy = |field: &[bool]| {
+                let state = GuideState::new(&guide, field, 6, boundary);
+                let squares = (0..field.len()).map(|c| {
+                    let mean = state.sums[c] as f64 / state.counts[c] as f64;
+                    (mean - state.targets[c]).powi(2)
+                });
+                squares.sum::<f64>() * guide.strength()
+            };
+
+            let mut state = GuideState::new(&guide, &field, 6, boundary);
+            for cell in [0, 7, 20, 35] {
+                let before = energy(&field);
+                let change = state.energy_change(cell, field[cell]);
+                state.flip(cell, field[cell]);
+                field[cell] = !field[cell];
+                assert!((energy(&field) - before - change).abs() < 1e-9);
+                assert_eq!(
+                    state.sums,
+                    GuideState::new(&guide, &field, 6, boundary).sums
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn guided_fields_follow_the_guide() {
+        let sample = resource("Cave");
+        let model = Arc::new(ConvChainModel::new(&sample, 3));
+
+        // Dense on the left, sparse on the right.
+        let guide = Guide::new(vec![0.9, 0.1, 0.9, 0.1], 2, 2, 50.);
+        let mut chain = ConvChain::from_model(model, 32, 1.0)
+            .with_seed(1)
+            .with_guide(guide);
+        chain.process(10);
+
+        let density = |columns: std::ops::Range<usize>| {
+            let field = chain.field();
+            let set = (0..32)
+                .flat_map(|y| columns.clone().map(move |x| y * 32 + x))
+                .filter(|&i| field[i])
+                .count();
+            set as f64 / (32 * columns.len()) as f64
+        };
+        assert!(density(0..16) > 0.7, "{}", density(0..16));
+        assert!(density(16..32) < 0.3, "{}", density(16..32));
+    }
+}
@@ -0,0 +1,407 @@
+use crate::{Boundary, ConvChainModel, ConvChainSample, PatternHistogram, Receptor, Symmetry};
+
+/// How the cells of a hex map are arranged in a sample.
+///
+/// Chains always generate fields in axial coordinates; with [`Boundary::Periodic`](crate::Boundary),
+/// such a field wraps around as a parallelogram and tiles the plane. An offset map cut from it
+/// wraps around at its own edges only if the field's parallelogram tiles the map's rectangle,
+/// see [`ConvChainSample::axial_to_hex_layout`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HexLayout {
+    /// Cell `(x, y)` is the hex with the axial coordinates `q = x`, `r = y`; every row is
+    /// shifted by half a hex relative to the previous one.
+    #[default]
+    Axial,
+    /// Rows of "pointy-top" hexes in offset coordinates, with every odd row shifted right
+    /// by half a hex. Such samples wrap around correctly only if their height is even.
+    OddRows,
+}
+
+/// The hexagonal symmetry group used to augment the patterns observed in a sample.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum HexSymmetry {
+    /// Only the patterns as they appear in the sample.
+    None,
+    /// The patterns and their rotations by multiples of 60°.
+    Rotation,
+    /// All rotations and reflections of the hexagon, i.e. the dihedral group D6.
+    #[default]
+    Full,
+}
+
+impl Receptor {
+    /// The cells within `radius` steps of a center hex in axial coordinates, e.g. 7 cells
+    /// for a radius of 1 and 19 cells for a radius of 2. The center is at `(radius, radius)`.
+    pub fn hexagon(radius: u32) -> Self {
+        let r = radius as i64;
+        let offsets = (-r..=r)
+            .flat_map(|dr| (-r..=r).map(move |dq| (dq, dr)))
+            .filter(|&(dq, dr)| (dq + dr).abs() <= r)
+            .map(|(dq, dr)| ((dq + r) as u32, (dr + r) as u32))
+            .collect();
+        Self::from_offsets(offsets).expect("the receptor has too many cells")
+    }
+}
+
+impl HexSymmetry {
+    /// The largest symmetry group contained in both `self` and `other`.
+    pub(crate) fn common(self, other: HexSymmetry) -> HexSymmetry {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (HexSymmetry::Full, other) | (other, HexSymmetry::Full) => other,
+            _ => HexSymmetry::None,
+        }
+    }
+
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            HexSymmetry::None => 0,
+            HexSymmetry::Rotation => 1,
+            HexSymmetry::Full => 2,
+        }
+    }
+
+    pub(crate) fn from_byte(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HexSymmetry::None),
+            1 => Some(HexSymmetry::Rotation),
+            2 => Some(HexSymmetry::Full),
+            _ => None,
+        }
+    }
+
+    /// The number of 60° rotations and whether to reflect for every element of the group.
+    fn transforms(self) -> &'static [(u32, bool)] {
+        match self {
+            HexSymmetry::None => &[(0, false)],
+            HexSymmetry::Rotation => &[
+                (0, false),
+                (1, false),
+                (2, false),
+                (3, false),
+                (4, false),
+                (5, false),
+            ],
+            HexSymmetry::Full => &[
+                (0, false),
+                (1, false),
+                (2, false),
+                (3, false),
+                (4, false),
+                (5, false),
+                (0, true),
+                (1, true),
+                (2, true),
+                (3, true),
+                (4, true),
+                (5, true),
+            ],
+        }
+    }
+}
+
+impl HexLayout {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            HexLayout::Axial => 0,
+            HexLayout::OddRows => 1,
+        }
+    }
+
+    pub(crate) fn from_byte(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(HexLayout::Axial),
+            1 => Some(HexLayout::OddRows),
+            _ => None,
+        }
+    }
+
+    /// Converts axial coordinates into sample coordinates, wrapping around at the edges.
+    fn to_sample(self, q: i64, r: i64, width: u32, height: u32) -> (u32, u32) {
+        let (x, y) = self.to_unwrapped(q, r);
+        (
+            x.rem_euclid(width as i64) as u32,
+            y.rem_euclid(height as i64) as u32,
+        )
+    }
+
+    fn to_unwrapped(self, q: i64, r: i64) -> (i64, i64) {
+        match self {
+            HexLayout::Axial => (q, r),
+            HexLayout::OddRows => (q + (r - (r & 1)) / 2, r),
+        }
+    }
+
+    fn to_axial(self, x: u32, y: u32) -> (i64, i64) {
+        let (x, y) = (x as i64, y as i64);
+        match self {
+            HexLayout::Axial => (x, y),
+            HexLayout::OddRows => (x - (y - (y & 1)) / 2, y),
+        }
+    }
+}
+
+impl ConvChainModel {
+    /// Learns the weights of the hexagonal patterns of the specified radius, see
+    /// [`Receptor::hexagon`]. The resulting model generates fields in axial coordinates.
+    pub fn learn_hex(
+        sample: &ConvChainSample,
+        layout: HexLayout,
+        radius: u32,
+        symmetry: HexSymmetry,
+        smoothing: f64,
+    ) -> Self {
+        let receptor = Receptor::hexagon(radius);
+        let mut weights = vec![smoothing; receptor.pattern_count()];
+        for_each_hex_pattern(
+            sample,
+            layout,
+            &receptor,
+            symmetry,
+            Boundary::Periodic,
+            |index| weights[index] += 1.,
+        );
+
+        // The hexagonal symmetries are already applied to the weights.
+        Self::from_weights(receptor, Symmetry::None, smoothing, weights).with_hex(layout, symmetry)
+    }
+}
+
+impl PatternHistogram {
+    /// Counts the hexagonal patterns of a sample and their symmetric variants, like
+    /// [`ConvChainModel::learn_hex`] learns them.
+    pub fn from_hex_sample(
+        sample: &ConvChainSample,
+        layout: HexLayout,
+        radius: u32,
+        symmetry: HexSymmetry,
+    ) -> Self {
+        let receptor = Receptor::hexagon(radius);
+        let mut counts = vec![0.; receptor.pattern_count()];
+        for_each_hex_pattern(
+            sample,
+            layout,
+            &receptor,
+            symmetry,
+            Boundary::Periodic,
+            |index| counts[index] += 1.,
+        );
+        Self::from_counts(receptor, counts)
+    }
+}
+
+/// Calls `f` with the weight table index of every pattern of a [hexagonal](Receptor::hexagon)
+/// receptor in the sample, once for every element of the symmetry group. Along axes where the
+/// boundary does not wrap around, only patterns lying completely within the sample are counted.
+pub(crate) fn for_each_hex_pattern<F>(
+    sample: &ConvChainSample,
+    layout: HexLayout,
+    receptor: &Receptor,
+    symmetry: HexSymmetry,
+    boundary: Boundary,
+    mut f: F,
+) where
+    F: FnMut(usize),
+{
+    let center = (receptor.width() / 2) as i64;
+    let axial: Vec<_> = receptor
+        .offsets()
+        .iter()
+        .map(|&(x, y)| (x as i64 - center, y as i64 - center))
+        .collect();
+
+    // Every transform permutes the cells of the hexagon.
+    let permutations: Vec<Vec<usize>> = symmetry
+        .transforms()
+        .iter()
+        .map(|&(rotations, reflected)| {
+            axial
+                .iter()
+                .map(|&cell| {
+                    let target = transform(cell, rotations, reflected);
+                    axial
+                        .iter()
+                        .position(|&c| c == target)
+                        .expect("hexagons are symmetric")
+                })
+                .collect()
+        })
+        .collect();
+
+    let (width, height) = (sample.width as i64, sample.height as i64);
+    let within = |(x, y): (i64, i64)| {
+        (boundary.wraps_horizontally() || (0..width).contains(&x))
+            && (boundary.wraps_vertically() || (0..height).contains(&y))
+    };
+    for y in 0..sample.height {
+        for x in 0..sample.width {
+            let (q, r) = layout.to_axial(x, y);
+            if !axial
+                .iter()
+                .all(|&(dq, dr)| within(layout.to_unwrapped(q + dq, r + dr)))
+            {
+                continue;
+            }
+            let values: Vec<bool> = axial
+                .iter()
+                .map(|&(dq, dr)| {
+                    sample[layout.to_sample(q + dq, r + dr, sample.width, sample.height)]
+                })
+                .collect();
+
+            for permutation in &permutations {
+                let index = values
+                    .iter()
+                    .zip(permutation)
+                    .filter(|(&value, _)| value)
+                    .fold(0, |index, (_, &bit)| index | (1 << bit));
+                f(index);
+            }
+        }
+    }
+}
+
+impl<'a> ConvChainSample<'a> {
+    /// Converts a field in axial coordinates, such as the output of a chain over a model
+    /// learned with [`ConvChainModel::learn_hex`], into a map of the specified layout and size.
+    ///
+    /// The field wraps around as a parallelogram, so maps of any size can be cut from it, but
+    /// the map itself tiles only for certain sizes. Going down two rows of an
+    /// [`OddRows`](HexLayout::OddRows) map moves one hex left in axial coordinates, so for an
+    /// `n × n` field, such maps tile if their width is a multiple of `n` and their height is
+    /// a multiple of `2n`. [`Axial`](HexLayout::Axial) maps tile if both are multiples of `n`.
+    pub fn axial_to_hex_layout(
+        &self,
+        layout: HexLayout,
+        width: u32,
+        height: u32,
+    ) -> ConvChainSample<'static> {
+        let values = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        ConvChainSample::from_row_major(
+            values.map(|(x, y)| {
+                let (q, r) = layout.to_axial(x, y);
+                self[HexLayout::Axial.to_sample(q, r, self.width, self.height)]
+            }),
+            width,
+            height,
+        )
+        .expect("the map has the requested size")
+    }
+}
+
+/// Rotates axial coordinates by multiples of 60°, after optionally reflecting them.
+fn transform((q, r): (i64, i64), rotations: u32, reflected: bool) -> (i64, i64) {
+    let (mut q, mut r) = if reflected { (r, q) } else { (q, r) };
+    for _ in 0..rotations {
+        (q, r) = (-r, q + r);
+    }
+    (q, r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::resource;
+    use crate::{ConvChain, PatternDictionary};
+    use std::sync::Arc;
+
+    /// Every third hex along each axis is set, which is invariant under D6.
+    fn sublattice(size: u32) -> ConvChainSample<'static> {
+        let values =
+            (0..size * size).map(|i| ((i % size) as i64 - (i / size) as i64).rem_euclid(3) == 0);
+        ConvChainSample::from_row_major(values, size, size).unwrap()
+    }
+
+    #[test]
+    fn hexagons_are_closed_under_d6() {
+        assert_eq!(Receptor::hexagon(1).len(), 7);
+        assert_eq!(Receptor::hexagon(2).len(), 19);
+
+        for &(rotations, reflected) in HexSymmetry::Full.transforms() {
+            let (q, r) = transform((2, -1), rotations, reflected);
+            assert_eq!([q.abs(), r.abs(), (q + r).abs()].iter().max(), Some(&2));
+        }
+        assert_eq!(transform((1, 0), 6, false), (1, 0));
+        assert_eq!(transform((1, 0), 1, false), (0, 1));
+    }
+
+    #[test]
+    fn layouts_learn_identical_weights() {
+        let axial = sublattice(6);
+        let offset = axial.axial_to_hex_layout(HexLayout::OddRows, 6, 6);
+        assert_eq!(offset.axial_to_hex_layout(HexLayout::Axial, 6, 6), offset);
+
+        let a = ConvChainModel::learn_hex(&axial, HexLayout::Axial, 1, HexSymmetry::Full, 0.1);
+        let b = ConvChainModel::learn_hex(&offset, HexLayout::OddRows, 1, HexSymmetry::Full, 0.1);
+        assert_eq!(a.weights(), b.weights());
+        assert_eq!(b.hex(), Some((HexLayout::OddRows, HexSymmetry::Full)));
+
+        // The sublattice is symmetric, so all 12 variants of every pattern coincide.
+        let seen: Vec<_> = a.weights().iter().filter(|&&w| w > 0.1).collect();
+        assert_eq!(seen.len(), 3);
+        assert!(seen.iter().all(|&&w| (w - 0.1 - 12. * 12.).abs() < 1e-9));
+    }
+
+    #[test]
+    fn hex_models_recount_samples_hexagonally() {
+        let sample = sublattice(6).axial_to_hex_layout(HexLayout::OddRows, 6, 6);
+        let model =
+            ConvChainModel::learn_hex(&sample, HexLayout::OddRows, 1, HexSymmetry::Rotation, 0.);
+
+        let mut data = Vec::new();
+        model.save(&mut data).unwrap();
+        assert_eq!(ConvChainModel::load(&data[..]).unwrap(), model);
+
+        // Training compares the model with this histogram of the sample.
+        let histogram = PatternHistogram::from_hex_sample(
+            &sample,
+            HexLayout::OddRows,
+            1,
+            HexSymmetry::Rotation,
+        );
+        assert_eq!(histogram.counts(), model.weights());
+
+        let dictionary = PatternDictionary::new(&model).with_sample(&sample, Boundary::Periodic);
+        assert_eq!(dictionary.iter().map(|e| e.count).sum::<usize>(), 6 * 6 * 6);
+        assert!(dictionary.iter().all(|e| e.variants.is_empty()));
+    }
+
+    #[test]
+    fn offset_maps_tile_at_twice_the_field_height() {
+        let axial = ConvChainSample::from_ascii("##..\n#...\n..#.\n.#..", &Default::default());
+        let axial = axial.unwrap();
+        let learn = |sample: &ConvChainSample, layout| {
+            ConvChainModel::learn_hex(sample, layout, 1, HexSymmetry::None, 0.)
+        };
+        let counts = learn(&axial, HexLayout::Axial);
+
+        // Learning from the offset map also counts the patterns across its seams.
+        let offset = axial.axial_to_hex_layout(HexLayout::OddRows, 4, 8);
+        let doubled: Vec<_> = counts.weights().iter().map(|w| 2. * w).collect();
+        assert_eq!(learn(&offset, HexLayout::OddRows).weights(), &doubled[..]);
+
+        let offset = axial.axial_to_hex_layout(HexLayout::OddRows, 4, 4);
+        assert_ne!(
+            learn(&offset, HexLayout::OddRows).weights(),
+            counts.weights()
+        );
+    }
+
+    #[test]
+    fn generation_learns_hexagonal_patterns() {
+        let sample = resource("Cave");
+        let model = Arc::new(ConvChainModel::learn_hex(
+            &sample,
+            HexLayout::Axial,
+            1,
+            HexSymmetry::Full,
+            0.1,
+        ));
+
+        let mut chain = ConvChain::from_model(model, 32, 1.0)
+            .with_seed(2)
+            .with_boundary(Boundary::Periodic);
+        let trace = chain.process_traced(10, None);
+        assert!(trace.last().unwrap().unseen_fraction < 0.25 * trace[0].unseen_fraction);
+    }
+}
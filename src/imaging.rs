@@ -0,0 +1,143 @@
+use crate::{ConvChainSample, PatternDictionary, SampleError};
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Pixel, Rgb, RgbImage};
+
+/// The colors used for unset and set values when rendering a field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Palette<P> {
+    pub unset: P,
+    pub set: P,
+}
+
+impl Default for Palette<Luma<u8>> {
+    fn default() -> Self {
+        Self {
+            unset: Luma([0]),
+            set: Luma([255]),
+        }
+    }
+}
+
+impl Default for Palette<Rgb<u8>> {
+    fn default() -> Self {
+        Self {
+            unset: Rgb([0, 0, 0]),
+            set: Rgb([255, 255, 255]),
+        }
+    }
+}
+
+impl ConvChainSample<'static> {
+    /// Creates a sample from a grayscale image; every pixel brighter than `threshold` is set.
+    pub fn from_luma_image(image: &GrayImage, threshold: u8) -> Result<Self, SampleError> {
+        Self::from_bytes(image.as_raw(), threshold, image.width(), image.height())
+    }
+
+    /// Creates a sample from an arbitrary image by converting it to grayscale first;
+    /// every pixel brighter than `threshold` is set.
+    pub fn from_dynamic_image(image: &DynamicImage, threshold: u8) -> Result<Self, SampleError> {
+        Self::from_luma_image(&image.to_luma8(), threshold)
+    }
+}
+
+impl<'a> ConvChainSample<'a> {
+    pub fn to_image<P>(&self, palette: &Palette<P>) -> ImageBuffer<P, Vec<P::Subpixel>>
+    where
+        P: Pixel + 'static,
+    {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            if self[(x, y)] {
+                palette.set
+            } else {
+                palette.unset
+            }
+        })
+    }
+
+    pub fn to_luma_image(&self, palette: &Palette<Luma<u8>>) -> GrayImage {
+        self.to_image(palette)
+    }
+
+    pub fn to_rgb_image(&self, palette: &Palette<Rgb<u8>>) -> RgbImage {
+        self.to_image(palette)
+    }
+}
+
+impl PatternDictionary {
+    /// Renders the patterns into a grid of `columns` cells, separated by `spacing` pixels
+    /// of the `separator` color, with every pattern element scaled to `scale × scale` pixels.
+    pub fn to_contact_sheet(
+        &self,
+        columns: u32,
+        scale: u32,
+        spacing: u32,
+        palette: &Palette<Rgb<u8>>,
+        separator: Rgb<u8>,
+    ) -> RgbImage {
+        assert!(columns > 0 && scale > 0);
+        let (width, height) = (
+            self.receptor().width() * scale,
+            self.receptor().height() * scale,
+        );
+        let (cell_width, cell_height) = (width + spacing, height + spacing);
+        let rows = (self.len() as u32).div_ceil(columns);
+        let mut sheet = RgbImage::from_pixel(
+            columns * cell_width + spacing,
+            rows * cell_height + spacing,
+            separator,
+        );
+
+        for (k, entry) in (0..).zip(self.iter()) {
+            let pattern = entry.pattern.to_rgb_image(palette);
+            let left = spacing + k % columns * cell_width;
+            let top = spacing + k / columns * cell_height;
+            for y in 0..height {
+                for x in 0..width {
+                    sheet.put_pixel(left + x, top + y, *pattern.get_pixel(x / scale, y / scale));
+                }
+            }
+        }
+        sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConvChainModel, Symmetry};
+
+    #[test]
+    fn luma_image_roundtrip() {
+        let image = GrayImage::from_raw(3, 2, vec![255, 0, 200, 0, 90, 0]).unwrap();
+        let sample = ConvChainSample::from_luma_image(&image, 100).unwrap();
+        assert_eq!(sample.as_slice(), [true, false, true, false, false, false]);
+
+        let rendered = sample.to_luma_image(&Palette::default());
+        assert_eq!(rendered.as_raw(), &[255, 0, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn rgb_image_uses_palette() {
+        let sample = ConvChainSample::new(&[true, false], 2, 1);
+        let palette = Palette {
+            unset: Rgb([10, 20, 30]),
+            set: Rgb([200, 100, 0]),
+        };
+        let rendered = sample.to_rgb_image(&palette);
+        assert_eq!(rendered.as_raw(), &[200, 100, 0, 10, 20, 30]);
+    }
+
+    #[test]
+    fn contact_sheet_places_patterns_in_a_grid() {
+        let sample = ConvChainSample::new(&[true, false, false, false], 2, 2);
+        let model = ConvChainModel::learn(&sample, 2, Symmetry::None, 0.1);
+        let dictionary = PatternDictionary::new(&model);
+        assert_eq!(dictionary.len(), 4);
+
+        let separator = Rgb([255, 0, 0]);
+        let sheet = dictionary.to_contact_sheet(3, 2, 1, &Palette::default(), separator);
+        assert_eq!(sheet.dimensions(), (3 * 5 + 1, 2 * 5 + 1));
+        assert_eq!(*sheet.get_pixel(0, 0), separator);
+        assert_eq!(*sheet.get_pixel(1, 1), Rgb([255, 255, 255]));
+        assert_eq!(*sheet.get_pixel(3, 1), Rgb([0, 0, 0]));
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{ConvChain, ConvChainModel, ConvChainSample};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The largest receptor size accepted in jobs; the weight table of a model
+/// has `2^(receptor_size²)` entries.
+pub const MAX_RECEPTOR_SIZE: u32 = 5;
+
+/// A list of generation jobs, such as the one in `resources/samples.xml`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct JobManifest {
+    pub samples: Vec<Job>,
+}
+
+/// A single generation job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    /// The name of the sample, e.g. `Cave` for `Cave.png`.
+    pub name: String,
+    #[serde(default = "default_receptor_size")]
+    pub receptor_size: u32,
+    #[serde(default = "default_temperature")]
+    pub temperature: f64,
+    #[serde(default = "default_iterations")]
+    pub iterations: usize,
+    /// The number of outputs to generate.
+    #[serde(default = "default_screenshot_count")]
+    pub screenshots: usize,
+    #[serde(default = "default_output_size")]
+    pub output_size: u32,
+    /// The seed of the first output; every subsequent output increments it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+/// The outputs generated for a single job.
+#[derive(Debug)]
+pub struct JobResult {
+    pub job: Job,
+    pub outputs: io::Result<Vec<ConvChainSample<'static>>>,
+}
+
+#[derive(Debug)]
+pub enum JobError {
+    Io(io::Error),
+    Xml(quick_xml::DeError),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    /// The manifest file extension is none of `xml`, `json` or `toml`.
+    UnknownFormat(PathBuf),
+    /// A job has invalid parameters.
+    InvalidJob {
+        index: usize,
+        reason: &'static str,
+    },
+}
+
+impl Display for JobError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Io(e) => write!(f, "{}", e),
+            JobError::Xml(e) => write!(f, "invalid XML manifest: {}", e),
+            JobError::Json(e) => write!(f, "invalid JSON manifest: {}", e),
+            JobError::Toml(e) => write!(f, "invalid TOML manifest: {}", e),
+            JobError::UnknownFormat(path) => {
+                write!(f, "unknown manifest format of {}", path.display())
+            }
+            JobError::InvalidJob { index, reason } => write!(f, "job {}: {}", index, reason),
+        }
+    }
+}
+
+impl Error for JobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JobError::Io(e) => Some(e),
+            JobError::Xml(e) => Some(e),
+            JobError::Json(e) => Some(e),
+            JobError::Toml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for JobError {
+    fn from(error: io::Error) -> Self {
+        JobError::Io(error)
+    }
+}
+
+/// The XML layout wraps every job in its own element.
+#[derive(Deserialize)]
+struct XmlManifest {
+    #[serde(rename = "$value", default)]
+    samples: Vec<Job>,
+}
+
+impl JobManifest {
+    pub fn from_xml_str(xml: &str) -> Result<Self, JobError> {
+        let manifest: XmlManifest = quick_xml::de::from_str(xml).map_err(JobError::Xml)?;
+        Ok(Self {
+            samples: manifest.samples,
+        })
+    }
+
+    pub fn from_json_str(json: &str) -> Result<Self, JobError> {
+        serde_json::from_str(json).map_err(JobError::Json)
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<Self, JobError> {
+        toml::from_str(toml).map_err(JobError::Toml)
+    }
+
+    /// Loads and validates a manifest, choosing the format by the file extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, JobError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        let manifest = match extension.as_deref() {
+            Some("xml") => Self::from_xml_str(&text),
+            Some("json") => Self::from_json_str(&text),
+            Some("toml") => Self::from_toml_str(&text),
+            _ => Err(JobError::UnknownFormat(path.to_path_buf())),
+        }?;
+
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<(), JobError> {
+        for (index, job) in self.samples.iter().enumerate() {
+            job.validate()
+                .map_err(|reason| JobError::InvalidJob { index, reason })?;
+        }
+        Ok(())
+    }
+
+    /// Validates and executes all jobs in parallel, obtaining each job's sample from `load_sample`.
+    pub fn execute<F>(&self, load_sample: F) -> Result<Vec<JobResult>, JobError>
+    where
+        F: Fn(&Job) -> io::Result<ConvChainSample<'static>> + Sync,
+    {
+        self.validate()?;
+        Ok(self
+            .samples
+            .par_iter()
+            .map(|job| JobResult {
+                job: job.clone(),
+                outputs: load_sample(job).map(|sample| job.execute(&sample)),
+            })
+            .collect())
+    }
+
+    /// Validates and executes all jobs in parallel, reading the samples from
+    /// `<directory>/<name>.png` (with the `image` feature) or `<directory>/<name>.pbm`.
+    pub fn execute_in<P: AsRef<Path>>(&self, directory: P) -> Result<Vec<JobResult>, JobError> {
+        let directory = directory.as_ref();
+        self.execute(|job| load_sample(directory, &job.name))
+    }
+}
+
+impl Job {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            receptor_size: default_receptor_size(),
+            temperature: default_temperature(),
+            iterations: default_iterations(),
+            screenshots: default_screenshot_count(),
+            output_size: default_output_size(),
+            seed: None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.name.is_empty() {
+            return Err("the sample name is empty");
+        }
+        if self.receptor_size == 0 || self.receptor_size > MAX_RECEPTOR_SIZE {
+            return Err("the receptor size is out of range");
+        }
+        if !(self.temperature.is_finite() && self.temperature > 0.) {
+            return Err("the temperature must be positive");
+        }
+        if self.output_size == 0 {
+            return Err("the output size must be positive");
+        }
+        Ok(())
+    }
+
+    /// Generates all outputs of this job in parallel.
+    pub fn execute(&self, sample: &ConvChainSample) -> Vec<ConvChainSample<'static>> {
+        let model = Arc::new(ConvChainModel::new(sample, self.receptor_size));
+        (0..self.screenshots)
+            .into_par_iter()
+            .map(|k| {
+                let mut chain =
+                    ConvChain::from_model(model.clone(), self.output_size, self.temperature);
+                if let Some(seed) = self.seed {
+                    chain = chain.with_seed(seed.wrapping_add(k as u64));
+                }
+                chain.process(self.iterations);
+                chain.as_sample().into_owned()
+            })
+            .collect()
+    }
+}
+
+fn load_sample(directory: &Path, name: &str) -> io::Result<ConvChainSample<'static>> {
+    #[cfg(feature = "image")]
+    {
+        let path = directory.join(format!("{}.png", name));
+        if path.exists() {
+            let image =
+                ::image::open(&path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            return ConvChainSample::from_dynamic_image(&image, 0)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+    }
+
+    let file = fs::File::open(directory.join(format!("{}.pbm", name)))?;
+    ConvChainSample::read_netpbm(io::BufReader::new(file), 0)
+}
+
+fn default_receptor_size() -> u32 {
+    2
+}
+
+fn default_temperature() -> f64 {
+    1.
+}
+
+fn default_iterations() -> usize {
+    2
+}
+
+fn default_screenshot_count() -> usize {
+    1
+}
+
+fn default_output_size() -> u32 {
+    32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_agree() {
+        let xml = r#"<samples>
+            <sample name="Cave" receptorSize="3" temperature="0.5" iterations="10" screenshots="8" outputSize="64"/>
+            <sample name="Chess"/>
+        </samples>"#;
+        let json = r#"{"samples": [
+            {"name": "Cave", "receptorSize": 3, "temperature": 0.5, "iterations": 10, "screenshots": 8, "outputSize": 64},
+            {"name": "Chess"}
+        ]}"#;
+        let toml = r#"
+            [[samples]]
+            name = "Cave"
+            receptorSize = 3
+            temperature = 0.5
+            iterations = 10
+            screenshots = 8
+            outputSize = 64
+
+            [[samples]]
+            name = "Chess"
+        "#;
+
+        let expected = JobManifest {
+            samples: vec![
+                Job {
+                    receptor_size: 3,
+                    temperature: 0.5,
+                    iterations: 10,
+                    screenshots: 8,
+                    output_size: 64,
+                    ..Job::new("Cave")
+                },
+                Job::new("Chess"),
+            ],
+        };
+
+        assert_eq!(JobManifest::from_xml_str(xml).unwrap(), expected);
+        assert_eq!(JobManifest::from_json_str(json).unwrap(), expected);
+        assert_eq!(JobManifest::from_toml_str(toml).unwrap(), expected);
+    }
+
+    #[test]
+    fn validation_reports_job_index() {
+        let manifest = JobManifest {
+            samples: vec![
+                Job::new("Cave"),
+                Job {
+                    temperature: 0.,
+                    ..Job::new("Maze")
+                },
+            ],
+        };
+
+        assert!(matches!(
+            manifest.validate(),
+            Err(JobError::InvalidJob { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn executes_resource_manifest() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let mut manifest = JobManifest::load(resources.join("samples.xml")).unwrap();
+        assert_eq!(manifest.samples.len(), 13);
+
+        manifest.samples.truncate(3);
+        for job in manifest.samples.iter_mut() {
+            job.iterations = 1;
+            job.seed = Some(1);
+        }
+
+        let results = manifest.execute_in(&resources).unwrap();
+        for (result, job) in results.iter().zip(&manifest.samples) {
+            let outputs = result.outputs.as_ref().unwrap();
+            assert_eq!(outputs.len(), job.screenshots);
+            assert!(outputs.iter().all(|o| o.width == job.output_size));
+        }
+    }
+
+    #[test]
+    fn samples_load_from_any_format() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+        let file = fs::File::open(resources.join("Maze.pbm")).unwrap();
+        let expected = ConvChainSample::read_netpbm(io::BufReader::new(file), 0).unwrap();
+
+        // With the `image` feature, the PNG is preferred and must be decodable.
+        assert_eq!(load_sample(&resources, "Maze").unwrap(), expected);
+    }
+}
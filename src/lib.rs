@@ -1,6 +1,60 @@
+mod ascii;
+mod classify;
+mod connectivity;
+mod diagnostics;
+mod dictionary;
 mod fast;
+mod guide;
+mod hex;
+#[cfg(feature = "image")]
+mod imaging;
+#[cfg(feature = "jobs")]
+mod job;
+mod metrics;
+mod model;
+mod netpbm;
+mod pyramid;
+mod receptor;
 mod sample;
+// The reference implementation is kept as it was ported.
+#[allow(
+    clippy::let_and_return,
+    clippy::needless_range_loop,
+    clippy::ptr_arg,
+    clippy::too_many_arguments
+)]
 pub mod slow;
+#[cfg(test)]
+mod test_support;
+mod training;
+mod voxel;
+mod world;
 
-pub use fast::ConvChain;
-pub use sample::ConvChainSample;
+pub use ascii::AsciiMapping;
+pub use classify::Classifier;
+pub use connectivity::{Carving, Components, Connectivity, ConnectivityConstraint};
+pub use diagnostics::{PlateauDetector, SweepStatistics};
+pub use dictionary::{PatternDictionary, PatternEntry};
+pub use fast::{Boundary, ConvChain};
+pub use guide::Guide;
+pub use hex::{HexLayout, HexSymmetry};
+#[cfg(feature = "image")]
+pub use imaging::Palette;
+#[cfg(feature = "jobs")]
+pub use job::{Job, JobError, JobManifest, JobResult, MAX_RECEPTOR_SIZE};
+pub use metrics::{PatternDistance, PatternHistogram};
+pub use model::{ConvChainModel, Interpolation, Symmetry, SymmetryVariant, DEFAULT_SMOOTHING};
+pub use netpbm::NetpbmFormat;
+pub use pyramid::Pyramid;
+pub use receptor::{Receptor, ReceptorError, MAX_RECEPTOR_CELLS};
+pub use sample::{ConvChainSample, SampleError};
+pub use training::Training;
+pub use voxel::{
+    VoxelBoundary, VoxelChain, VoxelModel, VoxelSample, VoxelSymmetry, MAX_VOXEL_RECEPTOR_SIZE,
+};
+pub use world::World;
+
+/// Compiles the code examples of the README.
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+pub struct ReadmeDoctests;
@@ -0,0 +1,198 @@
+use crate::model::for_each_pattern;
+use crate::{Boundary, ConvChainSample, Receptor, Symmetry};
+
+/// The probability assumed for patterns that never occur in a reference histogram,
+/// which keeps the KL divergence finite.
+const UNSEEN_PROBABILITY: f64 = 1e-9;
+
+/// The number of occurrences of every pattern of a receptor in a field,
+/// indexed like the weights of a [`ConvChainModel`](crate::ConvChainModel).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternHistogram {
+    receptor: Receptor,
+    counts: Vec<f64>,
+    total: f64,
+}
+
+/// Distance measures between the pattern distributions of two histograms.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PatternDistance {
+    /// The Kullback-Leibler divergence `D(P ‖ Q)` of the histogram from the reference.
+    pub kl_divergence: f64,
+    /// The total variation distance `½ Σ |p - q|`, ranging from 0 to 1.
+    pub total_variation: f64,
+    /// The symmetric χ² distance `½ Σ (p - q)² / (p + q)`, ranging from 0 to 1.
+    pub chi_squared: f64,
+    /// The fraction of pattern occurrences that never occur in the reference.
+    pub unseen_fraction: f64,
+}
+
+impl PatternHistogram {
+    /// Counts the patterns of a generated field.
+    pub fn from_field(
+        field: &ConvChainSample,
+        receptor: impl Into<Receptor>,
+        boundary: Boundary,
+    ) -> Self {
+        Self::count(field, receptor.into(), Symmetry::None, boundary)
+    }
+
+    /// Counts the patterns of a sample and their symmetric variants, like a model would learn them.
+    pub fn from_sample(
+        sample: &ConvChainSample,
+        receptor: impl Into<Receptor>,
+        symmetry: Symmetry,
+    ) -> Self {
+        Self::count(sample, receptor.into(), symmetry, Boundary::Periodic)
+    }
+
+    pub(crate) fn from_counts(receptor: Receptor, counts: Vec<f64>) -> Self {
+        let total = counts.iter().sum();
+        Self {
+            receptor,
+            counts,
+            total,
+        }
+    }
+
+    fn count(
+        sample: &ConvChainSample,
+        receptor: Receptor,
+        symmetry: Symmetry,
+        boundary: Boundary,
+    ) -> Self {
+        let mut counts = vec![0.; receptor.pattern_count()];
+        let mut total = 0.;
+        for_each_pattern(sample, &receptor, symmetry, boundary, |_, _, index| {
+            counts[index] += 1.;
+            total += 1.;
+        });
+
+        Self {
+            receptor,
+            counts,
+            total,
+        }
+    }
+
+    #[inline]
+    pub fn receptor(&self) -> &Receptor {
+        &self.receptor
+    }
+
+    #[inline]
+    pub fn counts(&self) -> &[f64] {
+        &self.counts
+    }
+
+    #[inline]
+    pub fn total(&self) -> f64 {
+        self.total
+    }
+
+    /// The relative frequency of the pattern with the specified index.
+    #[inline]
+    pub fn frequency(&self, index: usize) -> f64 {
+        if self.total > 0. {
+            self.counts[index] / self.total
+        } else {
+            0.
+        }
+    }
+
+    /// Compares this histogram's pattern distribution `P` to the one of a `reference` `Q`,
+    /// usually the histogram of the sample.
+    pub fn distance(&self, reference: &Self) -> PatternDistance {
+        assert_eq!(
+            self.receptor, reference.receptor,
+            "histograms must use the same receptor"
+        );
+
+        let mut distance = PatternDistance {
+            kl_divergence: 0.,
+            total_variation: 0.,
+            chi_squared: 0.,
+            unseen_fraction: 0.,
+        };
+
+        for index in 0..self.counts.len() {
+            let p = self.frequency(index);
+            let q = reference.frequency(index);
+
+            if p > 0. {
+                distance.kl_divergence += p * (p / q.max(UNSEEN_PROBABILITY)).ln();
+                if q == 0. {
+                    distance.unseen_fraction += p;
+                }
+            }
+            if p + q > 0. {
+                distance.chi_squared += (p - q) * (p - q) / (p + q);
+            }
+            distance.total_variation += (p - q).abs();
+        }
+
+        distance.total_variation *= 0.5;
+        distance.chi_squared *= 0.5;
+        distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::SIMPLE_MAZE;
+    use crate::{ConvChain, ConvChainModel};
+    use std::sync::Arc;
+
+    #[test]
+    fn identical_distributions_have_no_distance() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let histogram = PatternHistogram::from_sample(&sample, 2, Symmetry::Full);
+        assert_eq!(histogram.total(), 16. * 8.);
+
+        let distance = histogram.distance(&histogram);
+        assert!(distance.kl_divergence.abs() < 1e-12);
+        assert_eq!(distance.total_variation, 0.);
+        assert_eq!(distance.chi_squared, 0.);
+        assert_eq!(distance.unseen_fraction, 0.);
+    }
+
+    #[test]
+    fn open_boundary_ignores_wrapping_patterns() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let histogram = PatternHistogram::from_field(&sample, 3, Boundary::Open);
+        assert_eq!(histogram.total(), 4.);
+    }
+
+    #[test]
+    fn disjoint_distributions_are_maximally_distant() {
+        let black = ConvChainSample::new(&[false; 16], 4, 4);
+        let white = ConvChainSample::new(&[true; 16], 4, 4);
+        let distance = PatternHistogram::from_field(&black, 2, Boundary::Periodic)
+            .distance(&PatternHistogram::from_sample(&white, 2, Symmetry::Full));
+
+        assert_eq!(distance.total_variation, 1.);
+        assert_eq!(distance.chi_squared, 1.);
+        assert_eq!(distance.unseen_fraction, 1.);
+    }
+
+    #[test]
+    fn generation_approaches_sample_distribution() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let reference = PatternHistogram::from_sample(&sample, 2, Symmetry::Full);
+        let model = Arc::new(ConvChainModel::new(&sample, 2));
+        let mut chain = ConvChain::from_model(model, 32, 1.0).with_seed(7);
+
+        let distance = |chain: &ConvChain| {
+            PatternHistogram::from_field(&chain.as_sample(), 2, chain.boundary())
+                .distance(&reference)
+        };
+
+        let initial = distance(&chain);
+        chain.process(10);
+        let generated = distance(&chain);
+
+        assert!(generated.total_variation < initial.total_variation);
+        assert!(generated.kl_divergence < initial.kl_divergence);
+    }
+}
@@ -0,0 +1,783 @@
+use crate::receptor::MAX_RECEPTOR_CELLS;
+use crate::{Boundary, ConvChainSample, HexLayout, HexSymmetry, Receptor};
+use std::borrow::Cow;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"CCHM";
+const FORMAT_VERSION: u16 = 2;
+/// The flag in the symmetry byte that marks hex models.
+const HEX_FLAG: u8 = 0x10;
+
+pub const DEFAULT_SMOOTHING: f64 = 0.1;
+
+/// A transformation of a sample pattern, as applied by a [`Symmetry`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SymmetryVariant {
+    Identity,
+    Rotated90,
+    Rotated180,
+    Rotated270,
+    /// The horizontal reflection of the pattern.
+    Reflected,
+    /// The horizontal reflection of the pattern rotated by 90°.
+    Reflected90,
+    Reflected180,
+    Reflected270,
+}
+
+impl SymmetryVariant {
+    const ALL: [SymmetryVariant; 8] = [
+        SymmetryVariant::Identity,
+        SymmetryVariant::Rotated90,
+        SymmetryVariant::Rotated180,
+        SymmetryVariant::Rotated270,
+        SymmetryVariant::Reflected,
+        SymmetryVariant::Reflected90,
+        SymmetryVariant::Reflected180,
+        SymmetryVariant::Reflected270,
+    ];
+}
+
+impl Display for SymmetryVariant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SymmetryVariant::Identity => "identity",
+            SymmetryVariant::Rotated90 => "rotated 90°",
+            SymmetryVariant::Rotated180 => "rotated 180°",
+            SymmetryVariant::Rotated270 => "rotated 270°",
+            SymmetryVariant::Reflected => "reflected",
+            SymmetryVariant::Reflected90 => "reflected, rotated 90°",
+            SymmetryVariant::Reflected180 => "reflected, rotated 180°",
+            SymmetryVariant::Reflected270 => "reflected, rotated 270°",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The symmetry group used to augment the patterns observed in a sample.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Symmetry {
+    /// Only the patterns as they appear in the sample.
+    None,
+    /// The patterns and their horizontal reflections.
+    Reflection,
+    /// The patterns and their rotations by multiples of 90°.
+    Rotation,
+    /// All rotations and reflections.
+    #[default]
+    Full,
+}
+
+impl Symmetry {
+    fn variants(&self) -> &'static [usize] {
+        match self {
+            Symmetry::None => &[0],
+            Symmetry::Reflection => &[0, 4],
+            Symmetry::Rotation => &[0, 1, 2, 3],
+            Symmetry::Full => &[0, 1, 2, 3, 4, 5, 6, 7],
+        }
+    }
+
+    /// The largest symmetry group contained in both `self` and `other`.
+    fn common(self, other: Symmetry) -> Symmetry {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Symmetry::Full, other) | (other, Symmetry::Full) => other,
+            _ => Symmetry::None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Symmetry::None => 0,
+            Symmetry::Reflection => 1,
+            Symmetry::Rotation => 2,
+            Symmetry::Full => 3,
+        }
+    }
+
+    fn from_byte(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Symmetry::None),
+            1 => Some(Symmetry::Reflection),
+            2 => Some(Symmetry::Rotation),
+            3 => Some(Symmetry::Full),
+            _ => None,
+        }
+    }
+}
+
+/// The interpolation used when blending the weights of two models.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    /// `(1 - α)·a + α·b`
+    Arithmetic,
+    /// `a^(1 - α)·b^α`
+    Geometric,
+}
+
+impl Interpolation {
+    #[inline]
+    pub fn interpolate(self, a: f64, b: f64, alpha: f64) -> f64 {
+        match self {
+            Interpolation::Arithmetic => (1. - alpha) * a + alpha * b,
+            Interpolation::Geometric => a.powf(1. - alpha) * b.powf(alpha),
+        }
+    }
+}
+
+/// The pattern weights learned from a sample.
+///
+/// Learning a model is the expensive part of setting up a chain, so a model
+/// can be built once, shared between chains and persisted with [`ConvChainModel::save`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvChainModel {
+    receptor: Receptor,
+    symmetry: Symmetry,
+    smoothing: f64,
+    weights: Vec<f64>,
+    /// The layout of the sample and the symmetry of a model learned from a hex map.
+    hex: Option<(HexLayout, HexSymmetry)>,
+}
+
+impl ConvChainModel {
+    /// Learns a model with the specified receptor, e.g. `2` for `2 × 2` patterns
+    /// or [`Receptor::rectangle`].
+    pub fn new(sample: &ConvChainSample, receptor: impl Into<Receptor>) -> Self {
+        Self::learn(sample, receptor, Symmetry::default(), DEFAULT_SMOOTHING)
+    }
+
+    pub fn learn(
+        sample: &ConvChainSample,
+        receptor: impl Into<Receptor>,
+        symmetry: Symmetry,
+        smoothing: f64,
+    ) -> Self {
+        Self::learn_weighted(&[(sample, 1.0)], receptor, symmetry, smoothing, false)
+    }
+
+    /// Learns a model from multiple samples, scaling each sample's pattern counts
+    /// by its weight.
+    ///
+    /// If `normalize` is set, the counts of every sample are additionally scaled
+    /// by the inverse of its area (relative to the mean area of all samples), so that
+    /// large samples do not dominate the result.
+    pub fn learn_weighted(
+        samples: &[(&ConvChainSample, f64)],
+        receptor: impl Into<Receptor>,
+        symmetry: Symmetry,
+        smoothing: f64,
+        normalize: bool,
+    ) -> Self {
+        let receptor = receptor.into();
+        assert!(!samples.is_empty());
+
+        let mean_area = samples
+            .iter()
+            .map(|(sample, _)| sample.width as f64 * sample.height as f64)
+            .sum::<f64>()
+            / samples.len() as f64;
+
+        // Initialize all values to the smoothing term.
+        // This deviates from the original code in that _every_ weight is offset
+        // by it, but it saves an extra loop.
+        let mut weights = vec![smoothing; receptor.pattern_count()];
+
+        for (sample, weight) in samples {
+            assert!(*weight >= 0.);
+            let increment = if normalize {
+                weight * mean_area / (sample.width as f64 * sample.height as f64)
+            } else {
+                *weight
+            };
+
+            for_each_pattern(
+                sample,
+                &receptor,
+                symmetry,
+                Boundary::Periodic,
+                |_, _, index| weights[index] += increment,
+            );
+        }
+
+        Self {
+            receptor,
+            symmetry,
+            smoothing,
+            weights,
+            hex: None,
+        }
+    }
+
+    pub(crate) fn from_weights(
+        receptor: Receptor,
+        symmetry: Symmetry,
+        smoothing: f64,
+        weights: Vec<f64>,
+    ) -> Self {
+        assert_eq!(weights.len(), receptor.pattern_count());
+        Self {
+            receptor,
+            symmetry,
+            smoothing,
+            weights,
+            hex: None,
+        }
+    }
+
+    /// Creates a model whose weights interpolate between the weights of `a` (at `alpha = 0`)
+    /// and `b` (at `alpha = 1`).
+    pub fn blend(a: &Self, b: &Self, alpha: f64, interpolation: Interpolation) -> Self {
+        assert_eq!(
+            a.receptor, b.receptor,
+            "blended models must use the same receptor"
+        );
+        assert!((0. ..=1.).contains(&alpha));
+
+        let weights = a
+            .weights
+            .iter()
+            .zip(&b.weights)
+            .map(|(&wa, &wb)| interpolation.interpolate(wa, wb, alpha))
+            .collect();
+
+        Self {
+            receptor: a.receptor.clone(),
+            symmetry: a.symmetry.common(b.symmetry),
+            smoothing: interpolation.interpolate(a.smoothing, b.smoothing, alpha),
+            weights,
+            hex: a
+                .hex
+                .zip(b.hex)
+                .map(|((layout, a), (_, b))| (layout, a.common(b))),
+        }
+    }
+
+    #[inline]
+    pub fn receptor(&self) -> &Receptor {
+        &self.receptor
+    }
+
+    #[inline]
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// The layout of the sample and the hexagonal symmetry of a model learned with
+    /// [`Self::learn_hex`]. The weights of such models already include the symmetric variants,
+    /// so their [symmetry](Self::symmetry) is [`Symmetry::None`].
+    #[inline]
+    pub fn hex(&self) -> Option<(HexLayout, HexSymmetry)> {
+        self.hex
+    }
+
+    pub(crate) fn with_hex(mut self, layout: HexLayout, symmetry: HexSymmetry) -> Self {
+        self.hex = Some((layout, symmetry));
+        self
+    }
+
+    #[inline]
+    pub fn smoothing(&self) -> f64 {
+        self.smoothing
+    }
+
+    #[inline]
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    #[inline]
+    pub(crate) fn weights_mut(&mut self) -> &mut [f64] {
+        &mut self.weights
+    }
+
+    /// Determines whether the pattern with the specified index was observed in the sample.
+    ///
+    /// Unseen patterns keep the smoothing term as their weight, even in [trained](Self::train) models.
+    #[inline]
+    pub fn has_seen(&self, index: usize) -> bool {
+        self.weights[index] != self.smoothing
+    }
+
+    /// Determines the energy of a field, i.e. the sum of the log weights of all its patterns.
+    /// Higher values indicate fields that resemble the sample more closely.
+    pub fn energy(&self, field: &ConvChainSample, boundary: Boundary) -> f64 {
+        let mut energy = 0.;
+        for_each_pattern(
+            field,
+            &self.receptor,
+            Symmetry::None,
+            boundary,
+            |_, _, index| energy += self.weights[index].ln(),
+        );
+        energy
+    }
+
+    /// Determines the mean log probability of the field's patterns, with the weights normalized
+    /// to a probability distribution, divided by the receptor area. Unlike the energy, this
+    /// allows comparing fields of different sizes and models of different receptor sizes.
+    ///
+    /// A field without any complete pattern, e.g. one smaller than the receptor with an open
+    /// boundary, has no likelihood under the model; this returns negative infinity for it.
+    pub fn normalized_log_likelihood(&self, field: &ConvChainSample, boundary: Boundary) -> f64 {
+        let log_partition = self.weights.iter().sum::<f64>().ln();
+        let mut log_likelihood = 0.;
+        let mut patterns = 0usize;
+        for_each_pattern(
+            field,
+            &self.receptor,
+            Symmetry::None,
+            boundary,
+            |_, _, index| {
+                log_likelihood += self.weights[index].ln() - log_partition;
+                patterns += 1;
+            },
+        );
+
+        if patterns == 0 {
+            return f64::NEG_INFINITY;
+        }
+        log_likelihood / (patterns as f64 * self.receptor.len() as f64)
+    }
+
+    /// Determines the energy of every field element. The log weight of each pattern is
+    /// distributed evenly over the elements it covers, so the map sums up to [`Self::energy`].
+    pub fn energy_map(&self, field: &ConvChainSample, boundary: Boundary) -> Vec<f64> {
+        let (width, height) = (field.width, field.height);
+        let share = 1. / self.receptor.len() as f64;
+        let mut map = vec![0.; width as usize * height as usize];
+        for_each_pattern(
+            field,
+            &self.receptor,
+            Symmetry::None,
+            boundary,
+            |x, y, index| {
+                let energy = self.weights[index].ln() * share;
+                for &(dx, dy) in self.receptor.offsets() {
+                    map[(((y + dy) % height) * width + (x + dx) % width) as usize] += energy;
+                }
+            },
+        );
+        map
+    }
+
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.receptor.len() as u32).to_le_bytes())?;
+        for &(dx, dy) in self.receptor.offsets() {
+            writer.write_all(&dx.to_le_bytes())?;
+            writer.write_all(&dy.to_le_bytes())?;
+        }
+        // Hex models are marked by a flag in the symmetry byte.
+        let symmetry = match self.hex {
+            Some((layout, symmetry)) => HEX_FLAG | layout.to_byte() << 2 | symmetry.to_byte(),
+            None => self.symmetry.to_byte(),
+        };
+        writer.write_all(&[symmetry])?;
+        writer.write_all(&self.smoothing.to_le_bytes())?;
+
+        // Only weights deviating from the smoothing term are stored.
+        let entries: Vec<(u32, f64)> = self
+            .weights
+            .iter()
+            .enumerate()
+            .filter(|(_, &weight)| weight != self.smoothing)
+            .map(|(index, &weight)| (index as u32, weight))
+            .collect();
+
+        writer.write_all(&(entries.len() as u32).to_le_bytes())?;
+        for (index, weight) in entries {
+            writer.write_all(&index.to_le_bytes())?;
+            writer.write_all(&weight.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn load<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a ConvChain model"));
+        }
+
+        // Version 1 only supported square receptors and stored their size.
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        let receptor = match version {
+            1 => {
+                let size = u32::from_le_bytes(read_array(&mut reader)?);
+                if size == 0 || size as usize * size as usize > MAX_RECEPTOR_CELLS {
+                    return Err(invalid_data(format!("invalid receptor size {}", size)));
+                }
+                Receptor::square(size)
+            }
+            FORMAT_VERSION => {
+                let cells = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+                if cells > MAX_RECEPTOR_CELLS {
+                    return Err(invalid_data(format!("invalid receptor of {} cells", cells)));
+                }
+                let offsets = (0..cells)
+                    .map(|_| {
+                        let dx = u32::from_le_bytes(read_array(&mut reader)?);
+                        let dy = u32::from_le_bytes(read_array(&mut reader)?);
+                        Ok((dx, dy))
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                Receptor::from_offsets(offsets).map_err(invalid_data)?
+            }
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported model format version {}",
+                    version
+                )))
+            }
+        };
+
+        let [byte] = read_array(&mut reader)?;
+        let invalid_symmetry = || invalid_data(format!("invalid symmetry {}", byte));
+        let (symmetry, hex) = if byte & HEX_FLAG != 0 {
+            let layout = HexLayout::from_byte(byte >> 2 & 0b11).ok_or_else(invalid_symmetry)?;
+            let symmetry = HexSymmetry::from_byte(byte & 0b11).ok_or_else(invalid_symmetry)?;
+            (Symmetry::None, Some((layout, symmetry)))
+        } else {
+            (
+                Symmetry::from_byte(byte).ok_or_else(invalid_symmetry)?,
+                None,
+            )
+        };
+        let smoothing = f64::from_le_bytes(read_array(&mut reader)?);
+
+        // The entries are read before allocating the weight table, so that truncated files
+        // fail early, and an allocation failure is reported rather than aborting.
+        let count = u32::from_le_bytes(read_array(&mut reader)?);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let index = u32::from_le_bytes(read_array(&mut reader)?) as usize;
+            let weight = f64::from_le_bytes(read_array(&mut reader)?);
+            if index >= receptor.pattern_count() {
+                return Err(invalid_data(format!(
+                    "pattern index {} out of range",
+                    index
+                )));
+            }
+            entries.push((index, weight));
+        }
+
+        let mut weights = Vec::new();
+        weights
+            .try_reserve_exact(receptor.pattern_count())
+            .map_err(|e| io::Error::new(io::ErrorKind::OutOfMemory, e))?;
+        weights.resize(receptor.pattern_count(), smoothing);
+        for (index, weight) in entries {
+            weights[index] = weight;
+        }
+
+        Ok(Self {
+            receptor,
+            symmetry,
+            smoothing,
+            weights,
+            hex,
+        })
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut buffer = [0u8; N];
+    reader.read_exact(&mut buffer)?;
+    Ok(buffer)
+}
+
+pub(crate) fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Calls `f` with the origin and weight table index of every pattern of the sample, as well as
+/// of the patterns of the sample's symmetric variants. The origins of the latter refer to the
+/// transformed sample.
+pub(crate) fn for_each_pattern<F>(
+    sample: &ConvChainSample,
+    receptor: &Receptor,
+    symmetry: Symmetry,
+    boundary: Boundary,
+    mut f: F,
+) where
+    F: FnMut(u32, u32, usize),
+{
+    for_each_pattern_variant(sample, receptor, symmetry, boundary, |x, y, _, index| {
+        f(x, y, index)
+    });
+}
+
+/// Like [`for_each_pattern`], but also provides the variant each index stems from.
+///
+/// Rather than transforming every pattern, the whole sample is transformed, which works
+/// for receptors of any shape.
+pub(crate) fn for_each_pattern_variant<F>(
+    sample: &ConvChainSample,
+    receptor: &Receptor,
+    symmetry: Symmetry,
+    boundary: Boundary,
+    mut f: F,
+) where
+    F: FnMut(u32, u32, SymmetryVariant, usize),
+{
+    for &k in symmetry.variants() {
+        let variant = SymmetryVariant::ALL[k];
+        let sample = transform(sample, variant);
+        let boundary = if k % 2 == 1 {
+            boundary.transposed()
+        } else {
+            boundary
+        };
+
+        let max_x = if boundary.wraps_horizontally() {
+            sample.width
+        } else {
+            (sample.width + 1).saturating_sub(receptor.width())
+        };
+        let max_y = if boundary.wraps_vertically() {
+            sample.height
+        } else {
+            (sample.height + 1).saturating_sub(receptor.height())
+        };
+
+        for y in 0..max_y {
+            for x in 0..max_x {
+                f(
+                    x,
+                    y,
+                    variant,
+                    receptor.index(|dx, dy| sample[(x + dx, y + dy)]),
+                );
+            }
+        }
+    }
+}
+
+/// Rotates the sample by 90° the variant's number of times, then reflects it horizontally
+/// if required.
+fn transform<'s>(
+    sample: &'s ConvChainSample,
+    variant: SymmetryVariant,
+) -> Cow<'s, ConvChainSample<'s>> {
+    let (rotations, reflected) = match variant {
+        SymmetryVariant::Identity => return Cow::Borrowed(sample),
+        SymmetryVariant::Rotated90 => (1, false),
+        SymmetryVariant::Rotated180 => (2, false),
+        SymmetryVariant::Rotated270 => (3, false),
+        SymmetryVariant::Reflected => (0, true),
+        SymmetryVariant::Reflected90 => (1, true),
+        SymmetryVariant::Reflected180 => (2, true),
+        SymmetryVariant::Reflected270 => (3, true),
+    };
+
+    let mut result = sample.clone().into_owned();
+    for _ in 0..rotations {
+        result = rotate(&result);
+    }
+    if reflected {
+        result = reflect(&result);
+    }
+    Cow::Owned(result)
+}
+
+fn rotate(sample: &ConvChainSample) -> ConvChainSample<'static> {
+    let (width, height) = (sample.width, sample.height);
+    let values = (0..width).flat_map(|y| (0..height).map(move |x| (x, y)));
+    ConvChainSample::from_row_major(
+        values.map(|(x, y)| sample[(width - 1 - y, x)]),
+        height,
+        width,
+    )
+    .expect("rotation preserves the sample size")
+}
+
+fn reflect(sample: &ConvChainSample) -> ConvChainSample<'static> {
+    let (width, height) = (sample.width, sample.height);
+    let values = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+    ConvChainSample::from_row_major(
+        values.map(|(x, y)| sample[(width - 1 - x, y)]),
+        width,
+        height,
+    )
+    .expect("reflection preserves the sample size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::SIMPLE_MAZE;
+
+    #[test]
+    fn index_matches_field_layout() {
+        let index = |p: [bool; 4]| Receptor::square(2).index(|dx, dy| p[(dy * 2 + dx) as usize]);
+        assert_eq!(index([true, false, false, false]), 1);
+        assert_eq!(index([false, false, false, true]), 8);
+        assert_eq!(index([true, true, false, true]), 11);
+    }
+
+    #[test]
+    fn rotated_samples_yield_rotated_patterns() {
+        // The set top left corner rotates counterclockwise into the bottom left.
+        let sample = ConvChainSample::new(&[true, false, false, false], 2, 2);
+        let mut rotations = Vec::new();
+        for_each_pattern_variant(
+            &sample,
+            &Receptor::square(2),
+            Symmetry::Rotation,
+            Boundary::Open,
+            |_, _, variant, index| rotations.push((variant, index)),
+        );
+
+        assert_eq!(
+            rotations,
+            [
+                (SymmetryVariant::Identity, 0b0001),
+                (SymmetryVariant::Rotated90, 0b0100),
+                (SymmetryVariant::Rotated180, 0b1000),
+                (SymmetryVariant::Rotated270, 0b0010),
+            ]
+        );
+    }
+
+    #[test]
+    fn rectangular_receptors_learn_rectangular_patterns() {
+        // Horizontal stripes consist of uniform rows only.
+        let stripes: Vec<_> = (0..16).map(|i| i / 4 % 2 == 0).collect();
+        let sample = ConvChainSample::new(&stripes, 4, 4);
+        let model = ConvChainModel::learn(&sample, Receptor::rectangle(3, 1), Symmetry::None, 0.);
+
+        assert_eq!(model.weights().len(), 8);
+        assert_eq!(model.weights()[0b000], 8.);
+        assert_eq!(model.weights()[0b111], 8.);
+        assert_eq!(model.weights().iter().sum::<f64>(), 16.);
+
+        // Rotating the sample by 90° and 270° yields vertical stripes, i.e. alternating rows.
+        let rotated =
+            ConvChainModel::learn(&sample, Receptor::rectangle(3, 1), Symmetry::Rotation, 0.);
+        assert_eq!(rotated.weights()[0b101], 16.);
+        assert_eq!(rotated.weights()[0b010], 16.);
+    }
+
+    #[test]
+    fn full_symmetry_counts_every_variant() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let model = ConvChainModel::new(&sample, 2);
+        let total: f64 = model.weights().iter().map(|w| w - DEFAULT_SMOOTHING).sum();
+        assert!((total - 16. * 8.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weighted_samples_scale_counts() {
+        let maze = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let single = ConvChainModel::learn(&maze, 2, Symmetry::Full, 0.);
+        let weighted = ConvChainModel::learn_weighted(
+            &[(&maze, 0.5), (&maze, 2.)],
+            2,
+            Symmetry::Full,
+            0.,
+            false,
+        );
+
+        for (a, b) in single.weights().iter().zip(weighted.weights()) {
+            assert!((a * 2.5 - b).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn normalized_samples_contribute_equally() {
+        let small = ConvChainSample::new(&[true; 4], 2, 2);
+        let large = ConvChainSample::new(&[false; 64], 8, 8);
+        let model = ConvChainModel::learn_weighted(
+            &[(&small, 1.), (&large, 1.)],
+            2,
+            Symmetry::None,
+            0.,
+            true,
+        );
+
+        assert_eq!(model.weights()[0b1111], model.weights()[0b0000]);
+    }
+
+    #[test]
+    fn blend_interpolates_between_models() {
+        let maze = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let a = ConvChainModel::learn(&maze, 2, Symmetry::Full, 0.1);
+        let b = ConvChainModel::learn(&maze, 2, Symmetry::Rotation, 0.1);
+
+        for &interpolation in &[Interpolation::Arithmetic, Interpolation::Geometric] {
+            assert_eq!(
+                ConvChainModel::blend(&a, &b, 0., interpolation).weights(),
+                a.weights()
+            );
+            assert_eq!(
+                ConvChainModel::blend(&a, &b, 1., interpolation).weights(),
+                b.weights()
+            );
+        }
+
+        let mixed = ConvChainModel::blend(&a, &b, 0.5, Interpolation::Geometric);
+        assert_eq!(mixed.symmetry(), Symmetry::Rotation);
+        for ((wa, wb), w) in a.weights().iter().zip(b.weights()).zip(mixed.weights()) {
+            assert!(((wa * wb).sqrt() - w).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn energy_map_sums_up_to_energy() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let model = ConvChainModel::new(&sample, 2);
+
+        for &boundary in &[Boundary::Periodic, Boundary::Open] {
+            let energy = model.energy(&sample, boundary);
+            let map: f64 = model.energy_map(&sample, boundary).iter().sum();
+            assert!((energy - map).abs() < 1e-9);
+        }
+
+        let noise = ConvChainSample::new(&[true, false, false, true], 2, 2);
+        let periodic = model.energy(&sample, Boundary::Periodic) / 16.;
+        assert!(model.energy(&noise, Boundary::Periodic) / 4. < periodic);
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let model = ConvChainModel::learn(&sample, 3, Symmetry::Rotation, 0.5);
+
+        let mut buffer = Vec::new();
+        model.save(&mut buffer).unwrap();
+        let loaded = ConvChainModel::load(buffer.as_slice()).unwrap();
+
+        assert_eq!(loaded, model);
+    }
+
+    #[test]
+    fn rectangular_receptors_roundtrip() {
+        let sample = ConvChainSample::new(&SIMPLE_MAZE, 4, 4);
+        let model = ConvChainModel::learn(&sample, Receptor::rectangle(4, 2), Symmetry::Full, 0.1);
+
+        let mut buffer = Vec::new();
+        model.save(&mut buffer).unwrap();
+        assert_eq!(ConvChainModel::load(buffer.as_slice()).unwrap(), model);
+    }
+
+    #[test]
+    fn load_rejects_foreign_data() {
+        let error = ConvChainModel::load(&b"PNG\0\0\0"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // A version 2 header with a receptor offset of `u32::MAX`.
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        let error = ConvChainModel::load(&data[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
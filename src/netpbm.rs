@@ -0,0 +1,301 @@
+use crate::model::invalid_data;
+use crate::ConvChainSample;
+use std::io::{self, Read, Write};
+
+/// The Netpbm formats supported for writing samples.
+///
+/// Bitmaps follow the Netpbm convention of `1` denoting black, i.e. set values
+/// (which render as white) are stored as `0`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NetpbmFormat {
+    /// Plain (ASCII) bitmap, `P1`.
+    PlainPbm,
+    /// Raw (binary) bitmap, `P4`.
+    Pbm,
+    /// Plain (ASCII) graymap, `P2`.
+    PlainPgm,
+    /// Raw (binary) graymap, `P5`.
+    Pgm,
+}
+
+impl NetpbmFormat {
+    fn magic(self) -> &'static str {
+        match self {
+            NetpbmFormat::PlainPbm => "P1",
+            NetpbmFormat::Pbm => "P4",
+            NetpbmFormat::PlainPgm => "P2",
+            NetpbmFormat::Pgm => "P5",
+        }
+    }
+}
+
+impl ConvChainSample<'static> {
+    /// Reads a PBM (`P1`, `P4`) or PGM (`P2`, `P5`) image. Gray values are scaled
+    /// to the range `0..=255`; every value brighter than `threshold` is set.
+    pub fn read_netpbm<R: Read>(mut reader: R, threshold: u8) -> io::Result<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut parser = Parser {
+            data: &data,
+            pos: 0,
+        };
+
+        let format = match parser.token()? {
+            b"P1" => NetpbmFormat::PlainPbm,
+            b"P2" => NetpbmFormat::PlainPgm,
+            b"P4" => NetpbmFormat::Pbm,
+            b"P5" => NetpbmFormat::Pgm,
+            _ => return Err(invalid_data("not a PBM or PGM image")),
+        };
+
+        let width = parser.number()?;
+        let height = parser.number()?;
+        let max_value = match format {
+            NetpbmFormat::PlainPbm | NetpbmFormat::Pbm => 1,
+            NetpbmFormat::PlainPgm | NetpbmFormat::Pgm => parser.number()?,
+        };
+        if max_value == 0 || max_value > u16::MAX as u32 {
+            return Err(invalid_data(format!("invalid maximum value {}", max_value)));
+        }
+
+        let len = width as usize * height as usize;
+        let values = match format {
+            NetpbmFormat::PlainPbm => (0..len)
+                .map(|_| parser.bit().map(|black| !black))
+                .collect::<io::Result<Vec<_>>>()?,
+            NetpbmFormat::Pbm => {
+                let sample =
+                    Self::from_packed(parser.raster()?, width, height).map_err(invalid_data)?;
+                sample.as_slice().iter().map(|&black| !black).collect()
+            }
+            NetpbmFormat::PlainPgm => (0..len)
+                .map(|_| parser.number())
+                .map(|value| value.map(|v| scale(v, max_value) > threshold))
+                .collect::<io::Result<Vec<_>>>()?,
+            NetpbmFormat::Pgm => {
+                let raster = parser.raster()?;
+                let bytes_per_value = if max_value > 255 { 2 } else { 1 };
+                if raster.len() != len * bytes_per_value {
+                    return Err(invalid_data("unexpected raster size"));
+                }
+                raster
+                    .chunks(bytes_per_value)
+                    .map(|v| v.iter().fold(0u32, |value, &b| (value << 8) | b as u32))
+                    .map(|v| scale(v, max_value) > threshold)
+                    .collect()
+            }
+        };
+
+        Self::from_vec(values, width, height).map_err(invalid_data)
+    }
+}
+
+impl<'a> ConvChainSample<'a> {
+    pub fn write_netpbm<W: Write>(&self, mut writer: W, format: NetpbmFormat) -> io::Result<()> {
+        write!(
+            writer,
+            "{}\n{} {}\n",
+            format.magic(),
+            self.width,
+            self.height
+        )?;
+        let rows = self.as_slice().chunks(self.width as usize);
+
+        match format {
+            NetpbmFormat::PlainPbm => {
+                for row in rows {
+                    // Plain files should not contain lines longer than 70 characters.
+                    for line in row.chunks(70) {
+                        let line: String =
+                            line.iter().map(|&v| if v { '0' } else { '1' }).collect();
+                        writeln!(writer, "{}", line)?;
+                    }
+                }
+            }
+            NetpbmFormat::Pbm => {
+                for row in rows {
+                    let packed: Vec<u8> = row
+                        .chunks(8)
+                        .map(|bits| {
+                            bits.iter()
+                                .enumerate()
+                                .filter(|(_, &v)| !v)
+                                .fold(0u8, |byte, (i, _)| byte | (0x80 >> i))
+                        })
+                        .collect();
+                    writer.write_all(&packed)?;
+                }
+            }
+            NetpbmFormat::PlainPgm => {
+                writeln!(writer, "255")?;
+                for row in rows {
+                    for line in row.chunks(17) {
+                        let line: Vec<&str> =
+                            line.iter().map(|&v| if v { "255" } else { "0" }).collect();
+                        writeln!(writer, "{}", line.join(" "))?;
+                    }
+                }
+            }
+            NetpbmFormat::Pgm => {
+                writeln!(writer, "255")?;
+                let bytes: Vec<u8> = self
+                    .as_slice()
+                    .iter()
+                    .map(|&v| if v { 255 } else { 0 })
+                    .collect();
+                writer.write_all(&bytes)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Parser<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+impl<'d> Parser<'d> {
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(&c) = self.data.get(self.pos) {
+            if c == b'#' {
+                while self.data.get(self.pos).is_some_and(|&c| c != b'\n') {
+                    self.pos += 1;
+                }
+            } else if c.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn token(&mut self) -> io::Result<&'d [u8]> {
+        self.skip_whitespace_and_comments();
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|c| !c.is_ascii_whitespace() && *c != b'#')
+        {
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected end of image",
+            ));
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn number(&mut self) -> io::Result<u32> {
+        let token = self.token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| invalid_data("expected a number"))
+    }
+
+    /// Reads a single plain PBM value, which need not be separated by whitespace.
+    fn bit(&mut self) -> io::Result<bool> {
+        self.skip_whitespace_and_comments();
+        let value = match self.data.get(self.pos) {
+            Some(b'0') => false,
+            Some(b'1') => true,
+            Some(_) => return Err(invalid_data("expected 0 or 1")),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected end of image",
+                ))
+            }
+        };
+        self.pos += 1;
+        Ok(value)
+    }
+
+    /// Provides the binary raster following the single whitespace character after the header.
+    fn raster(&mut self) -> io::Result<&'d [u8]> {
+        match self.data.get(self.pos) {
+            Some(c) if c.is_ascii_whitespace() => Ok(&self.data[self.pos + 1..]),
+            _ => Err(invalid_data("expected whitespace after header")),
+        }
+    }
+}
+
+fn scale(value: u32, max_value: u32) -> u8 {
+    (value.min(max_value) * 255 / max_value) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, File};
+    use std::path::Path;
+
+    const FORMATS: [NetpbmFormat; 4] = [
+        NetpbmFormat::PlainPbm,
+        NetpbmFormat::Pbm,
+        NetpbmFormat::PlainPgm,
+        NetpbmFormat::Pgm,
+    ];
+
+    fn resource_samples() -> Vec<(String, ConvChainSample<'static>)> {
+        let mut samples = Vec::new();
+        for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("resources")).unwrap()
+        {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "pbm") {
+                let sample = ConvChainSample::read_netpbm(File::open(&path).unwrap(), 127).unwrap();
+                samples.push((path.to_string_lossy().into_owned(), sample));
+            }
+        }
+        assert!(!samples.is_empty());
+        samples
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn resources_match_their_png_originals() {
+        for (path, sample) in resource_samples() {
+            let gray = ::image::open(Path::new(&path).with_extension("png"))
+                .unwrap()
+                .to_luma8();
+            let original =
+                ConvChainSample::from_bytes(gray.as_raw(), 0, gray.width(), gray.height()).unwrap();
+            assert_eq!(sample, original, "{}", path);
+        }
+    }
+
+    #[test]
+    fn resources_roundtrip_through_all_formats() {
+        for (path, sample) in resource_samples() {
+            for &format in &FORMATS {
+                let mut buffer = Vec::new();
+                sample.write_netpbm(&mut buffer, format).unwrap();
+                let read = ConvChainSample::read_netpbm(buffer.as_slice(), 127).unwrap();
+                assert_eq!(read, sample, "{} as {:?}", path, format);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_plain_files_with_comments() {
+        let pbm = b"P1\n# a comment\n3 2\n010\n1 0 1\n";
+        let sample = ConvChainSample::read_netpbm(&pbm[..], 127).unwrap();
+        assert_eq!(sample.as_slice(), [true, false, true, false, true, false]);
+
+        let pgm = b"P2 3 1 15 15 7 8";
+        let sample = ConvChainSample::read_netpbm(&pgm[..], 127).unwrap();
+        assert_eq!(sample.as_slice(), [true, false, true]);
+    }
+
+    #[test]
+    fn rejects_truncated_rasters() {
+        let error = ConvChainSample::read_netpbm(&b"P4 9 2\n\x00\x00\x00"[..], 127).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{ConvChain, ConvChainModel, ConvChainSample, Receptor, Symmetry};
+use std::sync::Arc;
+
+/// Models learned from successively halved versions of a sample, for generating large fields
+/// from coarse to fine: the field generated at every level is upsampled to initialize the
+/// next one, so that large structures emerge within few iterations.
+#[derive(Debug, Clone)]
+pub struct Pyramid {
+    /// The models, finest level first.
+    models: Vec<Arc<ConvChainModel>>,
+}
+
+impl Pyramid {
+    /// Learns a model for each of `levels` resolutions of the sample, each level halving the
+    /// resolution of the previous one.
+    pub fn learn(
+        sample: &ConvChainSample,
+        receptor: impl Into<Receptor>,
+        levels: usize,
+        symmetry: Symmetry,
+        smoothing: f64,
+    ) -> Self {
+        assert!(levels > 0);
+        let receptor = receptor.into();
+        let mut sample = sample.clone().into_owned();
+        let mut models = Vec::with_capacity(levels);
+        for level in 0..levels {
+            if level > 0 {
+                sample = sample.downsample(2);
+            }
+            assert!(
+                sample.width >= receptor.width() && sample.height >= receptor.height(),
+                "the sample is too small for {} levels",
+                levels
+            );
+            models.push(Arc::new(ConvChainModel::learn(
+                &sample,
+                receptor.clone(),
+                symmetry,
+                smoothing,
+            )));
+        }
+
+        Self { models }
+    }
+
+    /// Creates a pyramid from models of the finest level first.
+    pub fn from_models(models: Vec<Arc<ConvChainModel>>) -> Self {
+        assert!(!models.is_empty());
+        Self { models }
+    }
+
+    #[inline]
+    pub fn levels(&self) -> usize {
+        self.models.len()
+    }
+
+    /// The models, finest level first.
+    #[inline]
+    pub fn models(&self) -> &[Arc<ConvChainModel>] {
+        &self.models
+    }
+
+    /// Generates a field of the specified size, which must be divisible by `2^(levels - 1)`,
+    /// running `iterations` at every level. Every level uses its own seed derived from `seed`.
+    pub fn generate(
+        &self,
+        output_size: u32,
+        temperature: f64,
+        iterations: usize,
+        seed: Option<u64>,
+    ) -> ConvChainSample<'static> {
+        let coarsest = self.models.len() - 1;
+        assert_eq!(
+            output_size % (1 << coarsest),
+            0,
+            "the output size must be divisible by 2^(levels - 1)"
+        );
+
+        let mut field: Option<ConvChainSample<'static>> = None;
+        for (level, model) in self.models.iter().enumerate().rev() {
+            let size = output_size >> level;
+            let mut chain = ConvChain::from_model(model.clone(), size, temperature);
+            if let Some(seed) = seed {
+                chain = chain.with_seed(seed.wrapping_add(level as u64));
+            }
+            if let Some(coarse) = &field {
+                chain = chain.with_field(&coarse.upsample(2));
+            }
+
+            chain.process(iterations);
+            field = Some(chain.as_sample().into_owned());
+        }
+
+        field.expect("the pyramid has at least one level")
+    }
+}
+
+impl<'a> ConvChainSample<'a> {
+    /// Reduces the resolution by the specified factor; every element of the result is set
+    /// if at least half of the `factor × factor` block it stems from is set.
+    pub fn downsample(&self, factor: u32) -> ConvChainSample<'static> {
+        assert!(factor > 0);
+        let (width, height) = (self.width.div_ceil(factor), self.height.div_ceil(factor));
+        let values = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        ConvChainSample::from_row_major(
+            values.map(|(x, y)| {
+                let block = (y * factor..((y + 1) * factor).min(self.height)).flat_map(|sy| {
+                    (x * factor..((x + 1) * factor).min(self.width)).map(move |sx| (sx, sy))
+                });
+                let (set, total) = block.fold((0, 0), |(set, total), (sx, sy)| {
+                    (set + self[(sx, sy)] as u32, total + 1)
+                });
+                2 * set >= total
+            }),
+            width,
+            height,
+        )
+        .expect("the result has the reduced size")
+    }
+
+    /// Increases the resolution by the specified factor, repeating every element.
+    pub fn upsample(&self, factor: u32) -> ConvChainSample<'static> {
+        assert!(factor > 0);
+        let (width, height) = (self.width * factor, self.height * factor);
+        let values = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        ConvChainSample::from_row_major(
+            values.map(|(x, y)| self[(x / factor, y / factor)]),
+            width,
+            height,
+        )
+        .expect("the result has the increased size")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::resource;
+    use crate::{AsciiMapping, PatternHistogram};
+
+    #[test]
+    fn resampling_keeps_blocks() {
+        let mapping = AsciiMapping::default();
+        let sample = ConvChainSample::from_ascii("##.#\n#...\n..##\n..#.", &mapping).unwrap();
+        let coarse = sample.downsample(2);
+        assert_eq!(coarse.to_ascii(&mapping), "#.\n.#\n");
+        assert_eq!(
+            coarse.upsample(2).to_ascii(&mapping),
+            "##..\n##..\n..##\n..##\n"
+        );
+    }
+
+    #[test]
+    fn coarse_levels_initialize_finer_ones() {
+        let sample = resource("Cave");
+        let pyramid = Pyramid::learn(&sample, 3, 3, Symmetry::Full, 0.1);
+        assert_eq!(pyramid.levels(), 3);
+
+        let first = pyramid.generate(64, 1.0, 2, Some(4));
+        assert_eq!((first.width, first.height), (64, 64));
+        assert_eq!(pyramid.generate(64, 1.0, 2, Some(4)), first);
+
+        // With the same number of iterations, the pyramid's output is closer to the sample
+        // than the output of a single chain.
+        let reference = PatternHistogram::from_sample(&sample, 3, Symmetry::Full);
+        let mut flat = ConvChain::from_model(pyramid.models()[0].clone(), 64, 1.0).with_seed(4);
+        flat.process(2);
+        let distance = |field: &ConvChainSample| {
+            PatternHistogram::from_field(field, 3, crate::Boundary::Periodic)
+                .distance(&reference)
+                .kl_divergence
+        };
+        assert!(distance(&first) < distance(&flat.as_sample()));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::ConvChainSample;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// The largest number of cells of a receptor; the weight table of a model
+/// has `2^cells` entries.
+pub const MAX_RECEPTOR_CELLS: usize = 31;
+
+/// The cells a pattern consists of, as offsets from the pattern's origin.
+///
+/// Bit `i` of a pattern's weight table index is the value of the `i`-th cell.
+/// For rectangular receptors, the cells are ordered row by row. Sparse receptors such as
+/// [dilated](Receptor::dilated) ones cover a larger area with the same number of cells,
+/// i.e. the same weight table size.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Receptor {
+    width: u32,
+    height: u32,
+    offsets: Vec<(u32, u32)>,
+}
+
+impl Receptor {
+    /// A `size × size` receptor.
+    pub fn square(size: u32) -> Self {
+        Self::rectangle(size, size)
+    }
+
+    /// A `width × height` receptor, e.g. `4 × 1` for horizontal structures.
+    pub fn rectangle(width: u32, height: u32) -> Self {
+        Self::dilated(width, height, 1)
+    }
+
+    /// A `width × height` grid of cells that are `stride` elements apart, e.g. a `3 × 3`
+    /// receptor spanning `5 × 5` elements for a stride of `2`.
+    pub fn dilated(width: u32, height: u32, stride: u32) -> Self {
+        assert!(width > 0 && height > 0, "the receptor must not be empty");
+        assert!(stride > 0, "the stride must be positive");
+        let offsets = (0..height)
+            .flat_map(|dy| (0..width).map(move |dx| (dx * stride, dy * stride)))
+            .collect();
+        Self::from_offsets(offsets).expect("the receptor has too many cells")
+    }
+
+    /// A plus-shaped receptor of the cells at most `radius` elements horizontally
+    /// or vertically from its center.
+    pub fn cross(radius: u32) -> Self {
+        let size = 2 * radius + 1;
+        let offsets = (0..size)
+            .flat_map(|dy| (0..size).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx == radius || dy == radius)
+            .collect();
+        Self::from_offsets(offsets).expect("the receptor has too many cells")
+    }
+
+    /// Creates a receptor of the set elements of a mask, in row-major order.
+    pub fn from_mask(mask: &ConvChainSample) -> Result<Self, ReceptorError> {
+        let offsets = (0..mask.height)
+            .flat_map(|dy| (0..mask.width).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| mask[(dx, dy)])
+            .collect();
+        Self::from_offsets(offsets)
+    }
+
+    /// Creates a receptor from distinct offsets; their order determines the bits
+    /// of the weight table index.
+    pub fn from_offsets(offsets: Vec<(u32, u32)>) -> Result<Self, ReceptorError> {
+        if offsets.is_empty() {
+            return Err(ReceptorError::Empty);
+        }
+        if offsets.len() > MAX_RECEPTOR_CELLS {
+            return Err(ReceptorError::TooManyCells(offsets.len()));
+        }
+        if let Some(i) = (1..offsets.len()).find(|&i| offsets[..i].contains(&offsets[i])) {
+            return Err(ReceptorError::DuplicateOffset(offsets[i]));
+        }
+
+        // Elements of the bounding box are indexed with `u32`s.
+        let (mut width, mut height) = (0u32, 0u32);
+        for &(dx, dy) in &offsets {
+            match (dx.checked_add(1), dy.checked_add(1)) {
+                (Some(w), Some(h)) if w.max(width).checked_mul(h.max(height)).is_some() => {
+                    width = w.max(width);
+                    height = h.max(height);
+                }
+                _ => return Err(ReceptorError::OffsetOutOfRange((dx, dy))),
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            offsets,
+        })
+    }
+
+    /// The width of the receptor's bounding box.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the receptor's bounding box.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn offsets(&self) -> &[(u32, u32)] {
+        &self.offsets
+    }
+
+    /// The number of cells of the receptor.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// The number of distinct patterns, i.e. the size of a model's weight table.
+    #[inline]
+    pub fn pattern_count(&self) -> usize {
+        1 << self.offsets.len()
+    }
+
+    /// Determines the weight table index of the pattern whose cell at an offset is `f(dx, dy)`.
+    #[inline]
+    pub(crate) fn index<F>(&self, f: F) -> usize
+    where
+        F: Fn(u32, u32) -> bool,
+    {
+        self.offsets
+            .iter()
+            .enumerate()
+            .filter(|(_, &(dx, dy))| f(dx, dy))
+            .fold(0, |index, (i, _)| index | (1 << i))
+    }
+
+    /// Decodes a weight table index into a row-major pattern of the receptor's bounding box.
+    /// Cells that are not part of the receptor are unset.
+    pub(crate) fn decode(&self, index: usize) -> Vec<bool> {
+        let mut pattern = vec![false; self.width as usize * self.height as usize];
+        for (i, &(dx, dy)) in self.offsets.iter().enumerate() {
+            pattern[(dy * self.width + dx) as usize] = index & (1 << i) != 0;
+        }
+        pattern
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceptorError {
+    /// The receptor has no cells.
+    Empty,
+    /// The receptor has more than [`MAX_RECEPTOR_CELLS`] cells.
+    TooManyCells(usize),
+    /// An offset occurs more than once.
+    DuplicateOffset((u32, u32)),
+    /// An offset is so large that the receptor's bounding box has more than `u32::MAX` elements.
+    OffsetOutOfRange((u32, u32)),
+}
+
+impl Display for ReceptorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReceptorError::Empty => write!(f, "the receptor has no cells"),
+            ReceptorError::TooManyCells(cells) => write!(
+                f,
+                "the receptor has {} cells, but at most {} are supported",
+                cells, MAX_RECEPTOR_CELLS
+            ),
+            ReceptorError::DuplicateOffset((dx, dy)) => {
+                write!(f, "the offset ({}, {}) occurs more than once", dx, dy)
+            }
+            ReceptorError::OffsetOutOfRange((dx, dy)) => {
+                write!(f, "the offset ({}, {}) is out of range", dx, dy)
+            }
+        }
+    }
+}
+
+impl Error for ReceptorError {}
+
+impl From<u32> for Receptor {
+    /// A square receptor of the specified size.
+    fn from(size: u32) -> Self {
+        Self::square(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangles_are_indexed_row_by_row() {
+        let receptor = Receptor::rectangle(3, 2);
+        assert_eq!((receptor.width(), receptor.height()), (3, 2));
+        assert_eq!(receptor.pattern_count(), 64);
+
+        let pattern = [true, false, false, false, true, true];
+        let index = receptor.index(|dx, dy| pattern[(dy * 3 + dx) as usize]);
+        assert_eq!(index, 0b110001);
+        assert_eq!(receptor.decode(index), pattern);
+    }
+
+    #[test]
+    fn masks_select_sparse_cells() {
+        let dilated = Receptor::dilated(2, 2, 3);
+        assert_eq!((dilated.width(), dilated.height()), (4, 4));
+        assert_eq!(dilated.offsets(), [(0, 0), (3, 0), (0, 3), (3, 3)]);
+
+        let mask = ConvChainSample::new(
+            &[false, true, false, true, true, true, false, true, false],
+            3,
+            3,
+        );
+        let cross = Receptor::from_mask(&mask).unwrap();
+        assert_eq!(cross, Receptor::cross(1));
+        assert_eq!(cross.pattern_count(), 32);
+        assert_eq!(cross.decode(0b11111), mask.as_slice());
+    }
+
+    #[test]
+    fn invalid_offsets_are_rejected() {
+        assert_eq!(
+            Receptor::from_offsets(Vec::new()),
+            Err(ReceptorError::Empty)
+        );
+        assert_eq!(
+            Receptor::from_offsets(vec![(0, 0), (1, 2), (0, 0)]),
+            Err(ReceptorError::DuplicateOffset((0, 0)))
+        );
+        assert_eq!(
+            Receptor::from_offsets((0..32).map(|x| (x, 0)).collect()),
+            Err(ReceptorError::TooManyCells(32))
+        );
+        assert_eq!(
+            Receptor::from_offsets(vec![(0, 0), (u32::MAX, 0)]),
+            Err(ReceptorError::OffsetOutOfRange((u32::MAX, 0)))
+        );
+        assert_eq!(
+            Receptor::from_offsets(vec![(1 << 16, 0), (0, 1 << 16)]),
+            Err(ReceptorError::OffsetOutOfRange((0, 1 << 16)))
+        );
+    }
+}
@@ -1,20 +1,177 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::ops::Index;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConvChainSample<'a> {
-    sample: &'a [bool],
+    sample: Cow<'a, [bool]>,
     pub width: u32,
     pub height: u32,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SampleError {
+    /// The sample has no width or no height.
+    Empty,
+    /// The number of values does not match the sample dimensions.
+    SizeMismatch { expected: usize, actual: usize },
+    /// A row's length differs from the length of the first row.
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// A character could not be mapped to a value.
+    InvalidCharacter {
+        row: usize,
+        column: usize,
+        character: char,
+    },
+    /// A character is mapped to both values.
+    AmbiguousCharacter { character: char },
+}
+
+impl Display for SampleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SampleError::Empty => write!(f, "the sample is empty"),
+            SampleError::SizeMismatch { expected, actual } => {
+                write!(f, "expected {} sample values, but got {}", expected, actual)
+            }
+            SampleError::RaggedRow {
+                row,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "row {} has {} values, but {} were expected",
+                row, actual, expected
+            ),
+            SampleError::InvalidCharacter {
+                row,
+                column,
+                character,
+            } => write!(
+                f,
+                "invalid character {:?} in row {}, column {}",
+                character, row, column
+            ),
+            SampleError::AmbiguousCharacter { character } => {
+                write!(f, "the character {:?} is mapped to both values", character)
+            }
+        }
+    }
+}
+
+impl Error for SampleError {}
+
 impl<'a> ConvChainSample<'a> {
     pub fn new(sample: &'a [bool], width: u32, height: u32) -> Self {
         assert_eq!(sample.len(), (width * height) as usize);
         Self {
-            sample,
+            sample: Cow::Borrowed(sample),
+            width,
+            height,
+        }
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[bool] {
+        &self.sample
+    }
+
+    pub fn into_owned(self) -> ConvChainSample<'static> {
+        ConvChainSample {
+            sample: Cow::Owned(self.sample.into_owned()),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+impl ConvChainSample<'static> {
+    /// Creates an owned sample from row-major values.
+    pub fn from_vec(sample: Vec<bool>, width: u32, height: u32) -> Result<Self, SampleError> {
+        validate_size(sample.len(), width, height)?;
+        Ok(Self {
+            sample: Cow::Owned(sample),
             width,
             height,
+        })
+    }
+
+    /// Creates an owned sample from row-major values, e.g. an iterator over pixels.
+    pub fn from_row_major<I>(values: I, width: u32, height: u32) -> Result<Self, SampleError>
+    where
+        I: IntoIterator<Item = bool>,
+    {
+        Self::from_vec(values.into_iter().collect(), width, height)
+    }
+
+    /// Creates an owned sample from row-major bytes; every byte greater than
+    /// `threshold` is set.
+    pub fn from_bytes(
+        bytes: &[u8],
+        threshold: u8,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, SampleError> {
+        Self::from_row_major(bytes.iter().map(|&b| b > threshold), width, height)
+    }
+
+    /// Creates an owned sample from a list of rows, all of which must have the same length.
+    pub fn from_rows(rows: Vec<Vec<bool>>) -> Result<Self, SampleError> {
+        let width = rows.first().map_or(0, Vec::len);
+        if let Some((row, values)) = rows.iter().enumerate().find(|(_, r)| r.len() != width) {
+            return Err(SampleError::RaggedRow {
+                row,
+                expected: width,
+                actual: values.len(),
+            });
+        }
+
+        let height = rows.len();
+        Self::from_vec(rows.concat(), width as u32, height as u32)
+    }
+
+    /// Creates an owned sample from bit-packed rows. Every row starts at a new byte,
+    /// with the first value of each byte in its most significant bit.
+    pub fn from_packed(bytes: &[u8], width: u32, height: u32) -> Result<Self, SampleError> {
+        if width == 0 || height == 0 {
+            return Err(SampleError::Empty);
+        }
+
+        let bytes_per_row = (width as usize).div_ceil(8);
+        let expected = bytes_per_row * height as usize;
+        if bytes.len() != expected {
+            return Err(SampleError::SizeMismatch {
+                expected,
+                actual: bytes.len(),
+            });
         }
+
+        let values = (0..height as usize).flat_map(|y| {
+            let row = &bytes[y * bytes_per_row..(y + 1) * bytes_per_row];
+            (0..width as usize).map(move |x| row[x / 8] & (0x80 >> (x % 8)) != 0)
+        });
+        Self::from_row_major(values, width, height)
+    }
+}
+
+fn validate_size(len: usize, width: u32, height: u32) -> Result<(), SampleError> {
+    if width == 0 || height == 0 {
+        return Err(SampleError::Empty);
+    }
+
+    let expected = width as usize * height as usize;
+    if len != expected {
+        return Err(SampleError::SizeMismatch {
+            expected,
+            actual: len,
+        });
     }
+    Ok(())
 }
 
 impl<'a> Index<usize> for ConvChainSample<'a> {
@@ -36,3 +193,54 @@ impl<'a> Index<(u32, u32)> for ConvChainSample<'a> {
         &self.sample[index as usize]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKER: [bool; 6] = [true, false, true, false, true, false];
+
+    #[test]
+    fn from_bytes_applies_threshold() {
+        let sample = ConvChainSample::from_bytes(&[200, 10, 255, 0, 128, 127], 127, 3, 2).unwrap();
+        assert_eq!(sample.as_slice(), CHECKER);
+    }
+
+    #[test]
+    fn from_rows_rejects_ragged_rows() {
+        let rows = vec![vec![true, false, true], vec![false, true]];
+        assert_eq!(
+            ConvChainSample::from_rows(rows),
+            Err(SampleError::RaggedRow {
+                row: 1,
+                expected: 3,
+                actual: 2
+            })
+        );
+    }
+
+    #[test]
+    fn from_packed_skips_row_padding() {
+        let sample = ConvChainSample::from_packed(&[0b1010_0000, 0b0100_0000], 3, 2).unwrap();
+        assert_eq!(sample.as_slice(), CHECKER);
+        assert_eq!(
+            ConvChainSample::from_packed(&[0], 3, 2),
+            Err(SampleError::SizeMismatch {
+                expected: 2,
+                actual: 1
+            })
+        );
+    }
+
+    #[test]
+    fn into_owned_outlives_borrow() {
+        let owned = {
+            let values = CHECKER.to_vec();
+            ConvChainSample::new(&values, 2, 3).into_owned()
+        };
+        assert_eq!(
+            owned,
+            ConvChainSample::from_vec(CHECKER.to_vec(), 2, 3).unwrap()
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use crate::{Boundary, ConvChainModel, ConvChainSample, PatternHistogram};
+use std::fs::File;
+use std::path::Path;
+
+pub(crate) const SIMPLE_MAZE: [bool; 16] = [
+    true, true, true, true, //
+    true, false, false, false, //
+    true, false, true, false, //
+    true, false, false, false, //
+];
+
+/// Reads `resources/<name>.pbm`.
+pub(crate) fn resource(name: &str) -> ConvChainSample<'static> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join(format!("{}.pbm", name));
+    ConvChainSample::read_netpbm(File::open(path).unwrap(), 127).unwrap()
+}
+
+/// The fraction of the field's patterns, without wrapping around, that the model has not seen.
+pub(crate) fn unseen_fraction(model: &ConvChainModel, field: &ConvChainSample) -> f64 {
+    let histogram = PatternHistogram::from_field(field, model.receptor().clone(), Boundary::Open);
+    let unseen: f64 = (0..histogram.counts().len())
+        .filter(|&index| !model.has_seen(index))
+        .map(|index| histogram.counts()[index])
+        .sum();
+    unseen / histogram.total()
+}
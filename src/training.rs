@@ -0,0 +1,115 @@
+use crate::{ConvChain, ConvChainModel, ConvChainSample, HexLayout, PatternHistogram};
+use std::sync::Arc;
+
+/// Parameters of [`ConvChainModel::train`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Training {
+    /// The number of weight updates.
+    pub epochs: usize,
+    /// The number of iterations the sampling chain runs between two updates.
+    pub sweeps: usize,
+    /// The fraction of the log frequency ratio applied to the log weights in every update.
+    pub learning_rate: f64,
+    /// The width and height of the field sampled from the model.
+    pub output_size: u32,
+    /// The seed of the sampling chain.
+    pub seed: u64,
+}
+
+impl Default for Training {
+    fn default() -> Self {
+        Self {
+            epochs: 50,
+            sweeps: 2,
+            learning_rate: 0.5,
+            output_size: 64,
+            seed: 0,
+        }
+    }
+}
+
+impl ConvChainModel {
+    /// Adjusts the weights so that fields generated at temperature 1 reproduce the pattern
+    /// frequencies of the sample, rather than merely using the pattern counts.
+    ///
+    /// Since the patterns of a field overlap, the count-based weights over- or underrepresent
+    /// patterns in the output. Training runs a persistent chain on the model and scales the weight
+    /// of every pattern observed in the sample by its (damped) frequency ratio between the sample
+    /// and the chain's field. Weights of unseen patterns are left at the smoothing term.
+    ///
+    /// [Hex models](Self::learn_hex) count the patterns of the sample in its layout
+    /// and with their hexagonal symmetry.
+    pub fn train(&self, sample: &ConvChainSample, training: &Training) -> Self {
+        let receptor = self.receptor().clone();
+        let histogram = |sample: &ConvChainSample, layout| match self.hex() {
+            Some((_, symmetry)) => {
+                PatternHistogram::from_hex_sample(sample, layout, receptor.width() / 2, symmetry)
+            }
+            None => PatternHistogram::from_sample(sample, receptor.clone(), self.symmetry()),
+        };
+        let layout = self.hex().map_or(HexLayout::Axial, |(layout, _)| layout);
+        let reference = histogram(sample, layout);
+
+        let mut model = self.clone();
+        let mut chain = ConvChain::from_model(Arc::new(model.clone()), training.output_size, 1.0)
+            .with_seed(training.seed);
+        chain.process(training.sweeps);
+
+        for _ in 0..training.epochs {
+            chain.process(training.sweeps);
+            // Chains generate hex maps in axial coordinates.
+            let generated = histogram(&chain.as_sample(), HexLayout::Axial);
+
+            // Patterns missing from the field count as half an occurrence.
+            let floor = 0.5 / generated.total();
+            for (index, weight) in model.weights_mut().iter_mut().enumerate() {
+                let expected = reference.frequency(index);
+                if expected > 0. {
+                    let actual = generated.frequency(index).max(floor);
+                    *weight *= (training.learning_rate * (expected / actual).ln()).exp();
+                }
+            }
+
+            chain.set_model(Arc::new(model.clone()));
+        }
+
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::resource;
+    use crate::Symmetry;
+
+    fn divergence(model: ConvChainModel, sample: &ConvChainSample) -> f64 {
+        let reference = PatternHistogram::from_sample(sample, 2, Symmetry::Full);
+        let model = Arc::new(model);
+        (0..4)
+            .map(|seed| {
+                let mut chain = ConvChain::from_model(model.clone(), 64, 1.0).with_seed(100 + seed);
+                chain.process(20);
+                PatternHistogram::from_sample(&chain.as_sample(), 2, Symmetry::Full)
+                    .distance(&reference)
+                    .kl_divergence
+            })
+            .sum::<f64>()
+            / 4.
+    }
+
+    #[test]
+    fn training_reduces_histogram_divergence() {
+        let sample = resource("Maze");
+        let model = ConvChainModel::new(&sample, 2);
+        let trained = model.train(&sample, &Training::default());
+
+        for index in 0..trained.weights().len() {
+            assert_eq!(trained.has_seen(index), model.has_seen(index));
+        }
+
+        let counted = divergence(model, &sample);
+        let trained = divergence(trained, &sample);
+        assert!(trained < counted, "{} >= {}", trained, counted);
+    }
+}
@@ -0,0 +1,567 @@
+use crate::{ConvChainSample, SampleError, DEFAULT_SMOOTHING};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Index;
+use std::sync::Arc;
+
+/// The largest receptor size of voxel models; patterns are indexed by 64-bit integers.
+pub const MAX_VOXEL_RECEPTOR_SIZE: u32 = 4;
+
+/// A three-dimensional sample, stored as consecutive `width × height` layers along the
+/// vertical `z` axis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoxelSample<'a> {
+    voxels: Cow<'a, [bool]>,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+
+/// The symmetry group used to augment the patterns observed in a voxel sample.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum VoxelSymmetry {
+    /// Only the patterns as they appear in the sample.
+    None,
+    /// The patterns and their rotations by multiples of 90° about the vertical axis.
+    VerticalRotation,
+    /// All 48 rotations and reflections of the cube.
+    #[default]
+    Full,
+}
+
+/// The axes along which a voxel field wraps around. Along the others, only receptors lying
+/// completely within the field are considered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct VoxelBoundary {
+    pub x: bool,
+    pub y: bool,
+    /// Whether the field wraps around along the vertical axis.
+    pub z: bool,
+}
+
+impl VoxelBoundary {
+    /// The field wraps around along all axes, which makes the output tileable in all directions.
+    pub const PERIODIC: Self = Self::from_axes(true, true, true);
+    /// Only receptors lying completely within the field are considered.
+    pub const OPEN: Self = Self::from_axes(false, false, false);
+    /// The field wraps around along the horizontal axes but has a floor and a ceiling,
+    /// e.g. for horizontally tileable caves.
+    pub const HORIZONTAL: Self = Self::from_axes(true, true, false);
+
+    /// Creates the boundary that wraps around along the specified axes.
+    pub const fn from_axes(x: bool, y: bool, z: bool) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl Default for VoxelBoundary {
+    fn default() -> Self {
+        Self::PERIODIC
+    }
+}
+
+/// Learned weights of `receptor_size³` voxel patterns.
+///
+/// Unlike [`ConvChainModel`](crate::ConvChainModel), only the weights of observed patterns
+/// are stored, since the dense table of e.g. `3 × 3 × 3` patterns would have `2^27` entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelModel {
+    receptor_size: u32,
+    symmetry: VoxelSymmetry,
+    smoothing: f64,
+    weights: HashMap<u64, f64>,
+}
+
+/// A Metropolis chain over a three-dimensional field.
+pub struct VoxelChain {
+    model: Arc<VoxelModel>,
+    one_over_temperature: f64,
+    width: usize,
+    height: usize,
+    depth: usize,
+    boundary: VoxelBoundary,
+    field: Vec<bool>,
+    rng: StdRng,
+}
+
+/// An axis permutation with optional mirroring of every axis, mapping a pattern onto a variant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Transform {
+    axes: [usize; 3],
+    mirrored: [bool; 3],
+}
+
+impl<'a> VoxelSample<'a> {
+    pub fn new(voxels: &'a [bool], width: u32, height: u32, depth: u32) -> Self {
+        assert_eq!(
+            voxels.len(),
+            width as usize * height as usize * depth as usize
+        );
+        Self {
+            voxels: Cow::Borrowed(voxels),
+            width,
+            height,
+            depth,
+        }
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[bool] {
+        &self.voxels
+    }
+
+    /// Provides the horizontal layer at height `z`.
+    pub fn layer(&self, z: u32) -> ConvChainSample<'_> {
+        let len = self.width as usize * self.height as usize;
+        let start = z as usize * len;
+        ConvChainSample::new(&self.voxels[start..start + len], self.width, self.height)
+    }
+
+    pub fn into_owned(self) -> VoxelSample<'static> {
+        VoxelSample {
+            voxels: Cow::Owned(self.voxels.into_owned()),
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+        }
+    }
+}
+
+impl VoxelSample<'static> {
+    /// Creates an owned sample from consecutive layers of row-major values.
+    pub fn from_vec(
+        voxels: Vec<bool>,
+        width: u32,
+        height: u32,
+        depth: u32,
+    ) -> Result<Self, SampleError> {
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(SampleError::Empty);
+        }
+
+        let expected = width as usize * height as usize * depth as usize;
+        if voxels.len() != expected {
+            return Err(SampleError::SizeMismatch {
+                expected,
+                actual: voxels.len(),
+            });
+        }
+
+        Ok(Self {
+            voxels: Cow::Owned(voxels),
+            width,
+            height,
+            depth,
+        })
+    }
+
+    /// Stacks two-dimensional samples of equal size, the first one being the bottom layer.
+    pub fn from_layers(layers: &[ConvChainSample]) -> Result<Self, SampleError> {
+        let (width, height) = layers.first().map_or((0, 0), |l| (l.width, l.height));
+        let mut voxels = Vec::with_capacity(width as usize * height as usize * layers.len());
+        for layer in layers {
+            if (layer.width, layer.height) != (width, height) {
+                return Err(SampleError::SizeMismatch {
+                    expected: width as usize * height as usize,
+                    actual: layer.as_slice().len(),
+                });
+            }
+            voxels.extend_from_slice(layer.as_slice());
+        }
+
+        Self::from_vec(voxels, width, height, layers.len() as u32)
+    }
+}
+
+impl<'a> Index<(u32, u32, u32)> for VoxelSample<'a> {
+    type Output = bool;
+
+    /// Gets the voxel at the specified coordinates, wrapping around at the edges.
+    #[inline]
+    fn index(&self, (x, y, z): (u32, u32, u32)) -> &Self::Output {
+        let (x, y, z) = (x % self.width, y % self.height, z % self.depth);
+        &self.voxels[((z * self.height + y) * self.width + x) as usize]
+    }
+}
+
+impl VoxelSymmetry {
+    fn transforms(self) -> Vec<Transform> {
+        const PERMUTATIONS: [[usize; 3]; 6] = [
+            [0, 1, 2],
+            [1, 0, 2],
+            [0, 2, 1],
+            [2, 1, 0],
+            [1, 2, 0],
+            [2, 0, 1],
+        ];
+
+        let all = PERMUTATIONS.iter().flat_map(|&axes| {
+            (0..8).map(move |m| Transform {
+                axes,
+                mirrored: [m & 1 != 0, m & 2 != 0, m & 4 != 0],
+            })
+        });
+
+        match self {
+            VoxelSymmetry::None => vec![Transform::IDENTITY],
+            VoxelSymmetry::VerticalRotation => all
+                .filter(|t| t.axes[2] == 2 && !t.mirrored[2] && t.is_rotation())
+                .collect(),
+            VoxelSymmetry::Full => all.collect(),
+        }
+    }
+}
+
+impl Transform {
+    const IDENTITY: Transform = Transform {
+        axes: [0, 1, 2],
+        mirrored: [false; 3],
+    };
+
+    /// Determines whether the transform preserves handedness.
+    fn is_rotation(&self) -> bool {
+        // Transpositions, the odd permutations of three axes, have exactly one fixed axis.
+        let fixed_axes = (0..3).filter(|&axis| self.axes[axis] == axis).count();
+        let transpositions = if fixed_axes == 1 { 1 } else { 0 };
+        let mirrors = self.mirrored.iter().filter(|&&m| m).count();
+        (transpositions + mirrors) % 2 == 0
+    }
+
+    /// Determines the index of the transformed pattern.
+    fn apply(&self, index: u64, receptor_size: u32) -> u64 {
+        let r = receptor_size;
+        let mut result = 0;
+        for z in 0..r {
+            for y in 0..r {
+                for x in 0..r {
+                    let target = [x, y, z];
+                    let mut source = [0; 3];
+                    for axis in 0..3 {
+                        let c = target[axis];
+                        source[self.axes[axis]] = if self.mirrored[axis] { r - 1 - c } else { c };
+                    }
+
+                    if index & (1 << bit(source[0], source[1], source[2], r)) != 0 {
+                        result |= 1 << bit(x, y, z, r);
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+impl VoxelModel {
+    pub fn new(sample: &VoxelSample, receptor_size: u32) -> Self {
+        Self::learn(
+            sample,
+            receptor_size,
+            VoxelSymmetry::default(),
+            DEFAULT_SMOOTHING,
+        )
+    }
+
+    /// Learns the weights of all periodic `receptor_size³` patterns of the sample and
+    /// their symmetric variants.
+    pub fn learn(
+        sample: &VoxelSample,
+        receptor_size: u32,
+        symmetry: VoxelSymmetry,
+        smoothing: f64,
+    ) -> Self {
+        assert!(receptor_size > 0 && receptor_size <= MAX_VOXEL_RECEPTOR_SIZE);
+        let transforms = symmetry.transforms();
+        let mut weights = HashMap::new();
+
+        for z in 0..sample.depth {
+            for y in 0..sample.height {
+                for x in 0..sample.width {
+                    let index =
+                        pattern_index(receptor_size, |dx, dy, dz| sample[(x + dx, y + dy, z + dz)]);
+                    for transform in &transforms {
+                        *weights
+                            .entry(transform.apply(index, receptor_size))
+                            .or_insert(smoothing) += 1.;
+                    }
+                }
+            }
+        }
+
+        Self {
+            receptor_size,
+            symmetry,
+            smoothing,
+            weights,
+        }
+    }
+
+    #[inline]
+    pub fn receptor_size(&self) -> u32 {
+        self.receptor_size
+    }
+
+    #[inline]
+    pub fn symmetry(&self) -> VoxelSymmetry {
+        self.symmetry
+    }
+
+    #[inline]
+    pub fn smoothing(&self) -> f64 {
+        self.smoothing
+    }
+
+    /// The number of distinct patterns observed in the sample.
+    #[inline]
+    pub fn pattern_count(&self) -> usize {
+        self.weights.len()
+    }
+
+    /// Determines the weight of a pattern; bit `(dz · r + dy) · r + dx` of the index is
+    /// the voxel at the offset `(dx, dy, dz)` from the receptor's origin.
+    #[inline]
+    pub fn weight(&self, index: u64) -> f64 {
+        self.weights.get(&index).copied().unwrap_or(self.smoothing)
+    }
+
+    #[inline]
+    pub fn has_seen(&self, index: u64) -> bool {
+        self.weights.contains_key(&index)
+    }
+}
+
+impl VoxelChain {
+    pub fn new(
+        model: Arc<VoxelModel>,
+        width: u32,
+        height: u32,
+        depth: u32,
+        temperature: f64,
+    ) -> Self {
+        let mut rng = StdRng::from_entropy();
+        let len = width as usize * height as usize * depth as usize;
+        let field = Self::initialize_field(len, &mut rng);
+
+        Self {
+            model,
+            one_over_temperature: 1.0 / temperature,
+            width: width as usize,
+            height: height as usize,
+            depth: depth as usize,
+            boundary: VoxelBoundary::default(),
+            field,
+            rng,
+        }
+    }
+
+    /// Seeds the chain's random number generator and re-initializes the field from it,
+    /// making the results reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.field = Self::initialize_field(self.field.len(), &mut self.rng);
+        self
+    }
+
+    /// Sets the axes along which the field wraps around; with [`VoxelBoundary::OPEN`], only
+    /// receptors lying completely within the field are considered.
+    pub fn with_boundary(mut self, boundary: VoxelBoundary) -> Self {
+        self.boundary = boundary;
+        self
+    }
+
+    #[inline]
+    pub fn model(&self) -> &Arc<VoxelModel> {
+        &self.model
+    }
+
+    #[inline]
+    pub fn boundary(&self) -> VoxelBoundary {
+        self.boundary
+    }
+
+    #[inline]
+    pub fn field(&self) -> &[bool] {
+        &self.field
+    }
+
+    pub fn as_sample(&self) -> VoxelSample<'_> {
+        VoxelSample::new(
+            &self.field,
+            self.width as u32,
+            self.height as u32,
+            self.depth as u32,
+        )
+    }
+
+    pub fn process(&mut self, iterations: usize) -> &[bool] {
+        for _ in 0..iterations {
+            self.sweep();
+        }
+
+        &self.field
+    }
+
+    /// Performs one Metropolis step per voxel.
+    fn sweep(&mut self) {
+        for _ in 0..self.field.len() {
+            let cell = self.rng.gen_range(0..self.field.len());
+            let q = self.weight_ratio(cell);
+
+            // Metropolis algorithm: Always accept if q is at least 1, otherwise with a probability.
+            if q >= 1. || q.powf(self.one_over_temperature) > self.rng.gen() {
+                self.field[cell] = !self.field[cell];
+            }
+        }
+    }
+
+    /// Determines the ratio of the weights of all receptors covering the cell
+    /// after and before flipping it.
+    fn weight_ratio(&self, cell: usize) -> f64 {
+        let position = [
+            cell % self.width,
+            cell / self.width % self.height,
+            cell / (self.width * self.height),
+        ];
+        let sizes = [self.width, self.height, self.depth];
+        let wraps = [self.boundary.x, self.boundary.y, self.boundary.z];
+        let r = self.model.receptor_size as i64;
+
+        let ranges: Vec<_> = (0..3)
+            .map(|axis| {
+                let (min, max) = (position[axis] as i64 - r + 1, position[axis] as i64);
+                if wraps[axis] {
+                    (min, max)
+                } else {
+                    (min.max(0), max.min(sizes[axis] as i64 - r))
+                }
+            })
+            .collect();
+
+        let mut q = 1.;
+        for sz in ranges[2].0..=ranges[2].1 {
+            for sy in ranges[1].0..=ranges[1].1 {
+                for sx in ranges[0].0..=ranges[0].1 {
+                    let old = self.pattern_index([sx, sy, sz], None);
+                    let new = self.pattern_index([sx, sy, sz], Some(cell));
+                    if new != old {
+                        q *= self.model.weight(new) / self.model.weight(old);
+                    }
+                }
+            }
+        }
+        q
+    }
+
+    /// Determines the index of the pattern at the specified origin, optionally with a cell flipped.
+    fn pattern_index(&self, origin: [i64; 3], flipped: Option<usize>) -> u64 {
+        let wrap = |s: i64, d: u32, size: usize| (s + d as i64).rem_euclid(size as i64) as usize;
+        pattern_index(self.model.receptor_size, |dx, dy, dz| {
+            let x = wrap(origin[0], dx, self.width);
+            let y = wrap(origin[1], dy, self.height);
+            let z = wrap(origin[2], dz, self.depth);
+            let cell = (z * self.height + y) * self.width + x;
+            self.field[cell] != (flipped == Some(cell))
+        })
+    }
+
+    fn initialize_field(len: usize, rng: &mut StdRng) -> Vec<bool> {
+        (0..len).map(|_| rng.gen()).collect()
+    }
+}
+
+#[inline]
+fn bit(x: u32, y: u32, z: u32, receptor_size: u32) -> u32 {
+    (z * receptor_size + y) * receptor_size + x
+}
+
+fn pattern_index<F>(receptor_size: u32, f: F) -> u64
+where
+    F: Fn(u32, u32, u32) -> bool,
+{
+    let mut index = 0;
+    for dz in 0..receptor_size {
+        for dy in 0..receptor_size {
+            for dx in 0..receptor_size {
+                if f(dx, dy, dz) {
+                    index |= 1 << bit(dx, dy, dz, receptor_size);
+                }
+            }
+        }
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetry_groups_have_expected_orders() {
+        // A single voxel in a corner of a 2³ receptor has eight distinct images under
+        // the full group, but only four under rotations about the vertical axis.
+        let orbit = |symmetry: VoxelSymmetry| {
+            let mut images: Vec<_> = symmetry
+                .transforms()
+                .iter()
+                .map(|t| t.apply(1, 2))
+                .collect();
+            images.sort_unstable();
+            images.dedup();
+            images
+        };
+
+        assert_eq!(VoxelSymmetry::None.transforms().len(), 1);
+        assert_eq!(VoxelSymmetry::VerticalRotation.transforms().len(), 4);
+        assert_eq!(VoxelSymmetry::Full.transforms().len(), 48);
+        assert_eq!(orbit(VoxelSymmetry::VerticalRotation), [1, 2, 4, 8]);
+        assert_eq!(orbit(VoxelSymmetry::Full).len(), 8);
+    }
+
+    #[test]
+    fn generation_reproduces_layered_samples() {
+        // Alternating full and empty horizontal layers.
+        let voxels: Vec<_> = (0..4 * 4 * 4).map(|i| i / 16 % 2 == 0).collect();
+        let sample = VoxelSample::from_vec(voxels, 4, 4, 4).unwrap();
+        let model = Arc::new(VoxelModel::learn(
+            &sample,
+            2,
+            VoxelSymmetry::VerticalRotation,
+            0.1,
+        ));
+        assert_eq!(model.pattern_count(), 2);
+
+        for boundary in [
+            VoxelBoundary::PERIODIC,
+            VoxelBoundary::OPEN,
+            VoxelBoundary::HORIZONTAL,
+        ] {
+            let mut chain = VoxelChain::new(model.clone(), 8, 8, 8, 0.1)
+                .with_seed(1)
+                .with_boundary(boundary);
+            chain.process(20);
+
+            // Domain walls may remain, but every layer should be mostly full or empty,
+            // alternating between neighboring layers.
+            let field = chain.as_sample();
+            let majorities: Vec<_> = (0..8)
+                .map(|z| {
+                    let set = field.layer(z).as_slice().iter().filter(|&&v| v).count();
+                    assert!(
+                        set <= 8 || set >= 56,
+                        "{:?}, layer {}: {}",
+                        boundary,
+                        z,
+                        set
+                    );
+                    set >= 56
+                })
+                .collect();
+            assert!(
+                majorities.windows(2).all(|w| w[0] != w[1]),
+                "{:?}",
+                boundary
+            );
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::{Boundary, ConvChain, ConvChainModel, ConvChainSample};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// An unbounded field generated in square chunks on demand.
+///
+/// Every chunk is generated with a border of context around it, as wide as the largest receptor
+/// minus one. Where the border overlaps neighboring chunks the chunk depends on, it is fixed to
+/// their values, so that all patterns across the seams are sampled from the model as well.
+///
+/// The dependencies follow from the parity of the chunk coordinates: chunks with two even
+/// coordinates depend on no other chunk, chunks with an odd `x` and an even `y` depend on their
+/// left and right neighbors, chunks with an even `x` and an odd `y` on the neighbors above,
+/// below and diagonal to them, and chunks with two odd coordinates on all eight neighbors.
+/// Generating a chunk generates its dependencies first, so every chunk is determined by the
+/// world seed and its coordinates alone, no matter in which order chunks are generated, and
+/// chunks can be [removed](Self::remove) and regenerated later.
+#[derive(Debug, Clone)]
+pub struct World {
+    models: Vec<Arc<ConvChainModel>>,
+    chunk_size: u32,
+    temperature: f64,
+    iterations: usize,
+    seed: u64,
+    chunks: HashMap<(i64, i64), ConvChainSample<'static>>,
+}
+
+impl World {
+    pub fn new(model: Arc<ConvChainModel>, chunk_size: u32, temperature: f64, seed: u64) -> Self {
+        Self::from_models(vec![model], chunk_size, temperature, seed)
+    }
+
+    /// Creates a world whose chunks are generated from the sum of the models' energies,
+    /// see [`ConvChain::from_models`].
+    pub fn from_models(
+        models: Vec<Arc<ConvChainModel>>,
+        chunk_size: u32,
+        temperature: f64,
+        seed: u64,
+    ) -> Self {
+        assert!(!models.is_empty(), "the world requires a model");
+        assert!(chunk_size > 0);
+        Self {
+            models,
+            chunk_size,
+            temperature,
+            iterations: 10,
+            seed,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Sets the number of iterations for generating a chunk; the default is 10.
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    #[inline]
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// The width of the border of context around every chunk.
+    pub fn context(&self) -> u32 {
+        self.models
+            .iter()
+            .map(|model| model.receptor().width().max(model.receptor().height()) - 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The chunk at the specified chunk coordinates, if it was generated or inserted.
+    pub fn chunk(&self, x: i64, y: i64) -> Option<&ConvChainSample<'static>> {
+        self.chunks.get(&(x, y))
+    }
+
+    /// Adds a previously generated chunk, e.g. one loaded from disk.
+    pub fn insert(&mut self, x: i64, y: i64, chunk: ConvChainSample<'static>) {
+        assert_eq!(
+            (chunk.width, chunk.height),
+            (self.chunk_size, self.chunk_size),
+            "the chunk must have the chunk size"
+        );
+        self.chunks.insert((x, y), chunk);
+    }
+
+    /// Removes a chunk, e.g. to free memory; it can be regenerated identically.
+    pub fn remove(&mut self, x: i64, y: i64) -> Option<ConvChainSample<'static>> {
+        self.chunks.remove(&(x, y))
+    }
+
+    /// Generates the chunk at the specified chunk coordinates and the chunks it depends on,
+    /// unless they already exist.
+    pub fn generate(&mut self, x: i64, y: i64) -> &ConvChainSample<'static> {
+        if !self.chunks.contains_key(&(x, y)) {
+            let dependencies = dependencies(x, y);
+            for &(dx, dy) in &dependencies {
+                self.generate(dx, dy);
+            }
+            let chunk = self.generate_chunk(x, y, &dependencies);
+            self.chunks.insert((x, y), chunk);
+        }
+        &self.chunks[&(x, y)]
+    }
+
+    /// Stitches the chunks in the specified ranges of chunk coordinates into one field,
+    /// or returns `None` if any of them is missing.
+    pub fn stitch(&self, x: Range<i64>, y: Range<i64>) -> Option<ConvChainSample<'static>> {
+        let size = self.chunk_size as i64;
+        let (width, height) = ((x.end - x.start) * size, (y.end - y.start) * size);
+        let mut values = Vec::with_capacity((width.max(0) * height.max(0)) as usize);
+        for wy in y.start * size..y.end * size {
+            for wx in x.start * size..x.end * size {
+                values.push(self.value(wx, wy)?);
+            }
+        }
+        ConvChainSample::from_vec(values, width as u32, height as u32).ok()
+    }
+
+    fn generate_chunk(
+        &self,
+        x: i64,
+        y: i64,
+        dependencies: &[(i64, i64)],
+    ) -> ConvChainSample<'static> {
+        let context = self.context() as i64;
+        let size = self.chunk_size as i64;
+        let padded = size + 2 * context;
+
+        let chain = ConvChain::from_models(self.models.clone(), padded as u32, self.temperature)
+            .with_seed(chunk_seed(self.seed, x, y))
+            .with_boundary(Boundary::Open);
+
+        // Context from the dependencies is fixed; everything else starts out as noise.
+        let mut field = chain.field().to_vec();
+        let mut fixed = vec![false; field.len()];
+        for py in 0..padded {
+            for px in 0..padded {
+                let (wx, wy) = (x * size + px - context, y * size + py - context);
+                let chunk = (wx.div_euclid(size), wy.div_euclid(size));
+                if !dependencies.contains(&chunk) {
+                    continue;
+                }
+                if let Some(value) = self.value(wx, wy) {
+                    field[(py * padded + px) as usize] = value;
+                    fixed[(py * padded + px) as usize] = true;
+                }
+            }
+        }
+
+        let mut chain = chain
+            .with_field(&ConvChainSample::new(&field, padded as u32, padded as u32))
+            .with_fixed(&ConvChainSample::new(&fixed, padded as u32, padded as u32));
+        let field = chain.process(self.iterations);
+
+        let values = (context..context + size)
+            .flat_map(|py| (context..context + size).map(move |px| (py * padded + px) as usize));
+        ConvChainSample::from_row_major(values.map(|i| field[i]), self.chunk_size, self.chunk_size)
+            .expect("the chunk has the chunk size")
+    }
+
+    /// The value at the specified world coordinates, if its chunk exists.
+    fn value(&self, wx: i64, wy: i64) -> Option<bool> {
+        let size = self.chunk_size as i64;
+        let chunk = self.chunk(wx.div_euclid(size), wy.div_euclid(size))?;
+        Some(chunk[(wx.rem_euclid(size) as u32, wy.rem_euclid(size) as u32)])
+    }
+}
+
+/// The neighbors whose values form the context of a chunk, see [`World`].
+fn dependencies(x: i64, y: i64) -> Vec<(i64, i64)> {
+    let neighbors = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)));
+    let offsets: Vec<_> = match (x.rem_euclid(2), y.rem_euclid(2)) {
+        (0, 0) => Vec::new(),
+        (1, 0) => vec![(-1, 0), (1, 0)],
+        (0, 1) => neighbors.filter(|&(_, dy)| dy != 0).collect(),
+        _ => neighbors.filter(|&offset| offset != (0, 0)).collect(),
+    };
+    offsets
+        .into_iter()
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .collect()
+}
+
+/// Derives the seed of a chunk from the world seed and the chunk coordinates.
+fn chunk_seed(seed: u64, x: i64, y: i64) -> u64 {
+    mix(seed ^ mix(x as u64 ^ mix(y as u64)))
+}
+
+/// The SplitMix64 finalizer, a fixed function unlike the standard library's hashers.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{resource, unseen_fraction};
+    use crate::Symmetry;
+
+    /// Horizontal stripes of height two.
+    fn stripes() -> Arc<ConvChainModel> {
+        let values: Vec<_> = (0..64).map(|i| i / 8 % 4 < 2).collect();
+        let sample = ConvChainSample::new(&values, 8, 8);
+        Arc::new(ConvChainModel::learn(&sample, 3, Symmetry::None, 0.01))
+    }
+
+    #[test]
+    fn chunks_are_deterministic() {
+        let mut a = World::new(stripes(), 8, 0.2, 3);
+        let mut b = World::new(stripes(), 8, 0.2, 3);
+        for &(x, y) in &[(0, 0), (1, 0), (-1, 2)] {
+            assert_eq!(a.generate(x, y), b.generate(x, y));
+        }
+        let other = World::new(stripes(), 8, 0.2, 4).generate(0, 0).clone();
+        assert_ne!(a.chunk(0, 0), Some(&other));
+        assert!(a.stitch(0..2, 0..1).is_some());
+        assert!(a.stitch(0..2, 0..2).is_none());
+    }
+
+    #[test]
+    fn chunks_do_not_depend_on_the_generation_order() {
+        let mut a = World::new(stripes(), 8, 0.2, 5);
+        let mut b = World::new(stripes(), 8, 0.2, 5);
+        let order = [(0, 0), (1, 0), (0, 1), (1, 1), (-1, -1)];
+        for &(x, y) in &order {
+            a.generate(x, y);
+        }
+        for &(x, y) in order.iter().rev() {
+            b.generate(x, y);
+        }
+        for &(x, y) in &order {
+            assert_eq!(a.chunk(x, y), b.chunk(x, y));
+        }
+
+        let chunk = a.remove(1, 1).unwrap();
+        a.remove(0, 1);
+        assert_eq!(a.generate(1, 1), &chunk);
+        assert_eq!(a.chunk(0, 1), b.chunk(0, 1));
+    }
+
+    #[test]
+    fn chunks_stitch_without_seams() {
+        let sample = resource("Cave");
+        let model = Arc::new(ConvChainModel::new(&sample, 3));
+        let order = [(0, 0), (2, 0), (1, 1), (1, 0), (0, 1), (2, 1)];
+
+        let mut world = World::new(model.clone(), 16, 1.0, 1);
+        assert_eq!(world.context(), 2);
+        for &(x, y) in &order {
+            world.generate(x, y);
+        }
+
+        // Chunks of different worlds, which do not fit together.
+        let mut independent = World::new(model.clone(), 16, 1.0, 1);
+        for (seed, &(x, y)) in (2..).zip(&order) {
+            let chunk = World::new(model.clone(), 16, 1.0, seed)
+                .generate(x, y)
+                .clone();
+            independent.insert(x, y, chunk);
+        }
+
+        let stitched = world.stitch(0..3, 0..2).unwrap();
+        assert_eq!((stitched.width, stitched.height), (48, 32));
+        let seamless = unseen_fraction(&model, &stitched);
+        let seams = unseen_fraction(&model, &independent.stitch(0..3, 0..2).unwrap());
+        assert!(seamless < 0.2 * seams);
+    }
+}